@@ -16,11 +16,11 @@
 // under the License.
 
 #![cfg(feature = "axum")]
-use axum::routing::post;
 use axum::{routing::get, Router};
 use shenyu_client_rust::axum_impl::ShenYuRouter;
-use shenyu_client_rust::config::ShenYuConfig;
-use shenyu_client_rust::{core::ShenyuClient, IRouter};
+use shenyu_client_rust::core::ShenyuClient;
+use shenyu_client_rust::macros::shenyu_client;
+use shenyu_client_rust::shenyu_route;
 
 mod ci;
 use crate::ci::_CI_CTRL_C;
@@ -29,6 +29,7 @@ async fn health_handler() -> &'static str {
     "OK"
 }
 
+#[shenyu_client(path = "/users", method = "post", desc = "Creates a user")]
 async fn create_user_handler() -> &'static str {
     "User created"
 }
@@ -43,12 +44,16 @@ async fn main() {
     let app = ShenYuRouter::<()>::new("shenyu_client_app")
         .nest("/api", ShenYuRouter::new("api"))
         .route("/health", "get", get(health_handler))
-        .route("/users", "post", post(create_user_handler));
-    let config = ShenYuConfig::from_yaml_file("examples/config.yml").unwrap();
-    let client = ShenyuClient::from(config, app.app_name(), app.uri_infos(), 3000).unwrap();
+        .handler(shenyu_route!(create_user_handler));
+    let client = ShenyuClient::builder()
+        .config_file("examples/config.yml")
+        .router(&app)
+        .port(3000)
+        .build()
+        .unwrap();
 
     let axum_app: Router = app.into();
-    client.register().expect("TODO: panic message");
+    client.register_async().await.expect("TODO: panic message");
 
     // Start Axum server
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
@@ -57,7 +62,8 @@ async fn main() {
             tokio::signal::ctrl_c()
                 .await
                 .expect("failed to listen for event");
-            client.offline_register();
+            // failures are logged by the client
+            let _ = client.offline_register_async().await;
         })
         .await
         .unwrap();
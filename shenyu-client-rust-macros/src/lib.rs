@@ -0,0 +1,227 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The macros of `shenyu-client-rust`, use them through its `macros`
+//! feature rather than depending on this crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{parse_macro_input, Expr, ExprArray, ExprLit, ItemFn, Lit, LitBool, LitStr};
+
+/// The methods a route may be registered for.
+const METHODS: &[&str] = &[
+    "get", "post", "put", "delete", "patch", "head", "options", "trace",
+];
+
+/// Declares the route served by the handler it annotates, along with the
+/// metadata registered to ShenYu for it, as the Java client's
+/// `@ShenyuSpringMvcClient` does.
+///
+/// ```ignore
+/// #[shenyu_client(path = "/users", method = "post", desc = "Creates a user")]
+/// async fn create_user() -> &'static str {
+///     "User created"
+/// }
+/// ```
+///
+/// `path` and `method` are required; `rule_name` (`path` by default),
+/// `desc`, `service_name`, `parameter_types`, `rpc_ext`,
+/// `plugin_names = ["..."]` and `enabled` are optional.
+///
+/// The route is added to the registry of `shenyu_client_rust::macros`,
+/// and `shenyu_route!(handler)` pairs it with the handler for a
+/// `ShenYuRouter`.
+#[proc_macro_attribute]
+pub fn shenyu_client(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut route = Route::default();
+    let parser = syn::meta::parser(|meta| route.parse(meta));
+    parse_macro_input!(args with parser);
+    let handler = parse_macro_input!(item as ItemFn);
+    expand(route, handler)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The arguments of `#[shenyu_client]`.
+#[derive(Default)]
+struct Route {
+    path: Option<LitStr>,
+    method: Option<LitStr>,
+    rule_name: Option<LitStr>,
+    desc: Option<LitStr>,
+    service_name: Option<LitStr>,
+    parameter_types: Option<LitStr>,
+    rpc_ext: Option<LitStr>,
+    plugin_names: Vec<LitStr>,
+    enabled: Option<LitBool>,
+}
+
+impl Route {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        let Some(name) = meta.path.get_ident().map(ToString::to_string) else {
+            return Err(meta.error("expected an argument name"));
+        };
+        let field = match name.as_str() {
+            "path" => &mut self.path,
+            "method" => &mut self.method,
+            "rule_name" => &mut self.rule_name,
+            "desc" => &mut self.desc,
+            "service_name" => &mut self.service_name,
+            "parameter_types" => &mut self.parameter_types,
+            "rpc_ext" => &mut self.rpc_ext,
+            "plugin_names" => {
+                let names: ExprArray = meta.value()?.parse()?;
+                self.plugin_names = names
+                    .elems
+                    .into_iter()
+                    .map(|name| match name {
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(name),
+                            ..
+                        }) => Ok(name),
+                        other => Err(syn::Error::new_spanned(other, "expected a string")),
+                    })
+                    .collect::<syn::Result<_>>()?;
+                return Ok(());
+            }
+            "enabled" => {
+                self.enabled = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+            _ => {
+                return Err(meta.error(format!(
+                    "unknown argument {name}, expected one of path, method, rule_name, desc, \
+                     service_name, parameter_types, rpc_ext, plugin_names, enabled"
+                )))
+            }
+        };
+        *field = Some(meta.value()?.parse()?);
+        Ok(())
+    }
+}
+
+fn expand(route: Route, handler: ItemFn) -> syn::Result<TokenStream2> {
+    let name = &handler.sig.ident;
+    let path = route
+        .path
+        .ok_or_else(|| syn::Error::new_spanned(name, "#[shenyu_client] requires a path"))?;
+    let method = route
+        .method
+        .ok_or_else(|| syn::Error::new_spanned(name, "#[shenyu_client] requires a method"))?;
+    let method_name = method.value().to_lowercase();
+    if !METHODS.contains(&method_name.as_str()) {
+        return Err(syn::Error::new_spanned(
+            method,
+            format!("unknown method, expected one of {}", METHODS.join(", ")),
+        ));
+    }
+    let rule_name = route.rule_name.unwrap_or_else(|| path.clone());
+    let service_name = match route.service_name {
+        Some(service_name) => quote!(::std::option::Option::Some(#service_name.to_string())),
+        None => quote!(::std::option::Option::None),
+    };
+    let desc = route.desc.map(|desc| desc.value()).unwrap_or_default();
+    let parameter_types = route
+        .parameter_types
+        .map(|types| types.value())
+        .unwrap_or_default();
+    let rpc_ext = route.rpc_ext.map(|ext| ext.value()).unwrap_or_default();
+    let plugin_names = route.plugin_names;
+    let enabled = route.enabled.map_or(true, |enabled| enabled.value);
+    let vis = &handler.vis;
+    let registry = quote!(::shenyu_client_rust::macros);
+
+    // a braced struct lives in the type namespace only, so it takes the name
+    // of the handler, which `shenyu_route!` resolves both ways
+    Ok(quote! {
+        #handler
+
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #vis struct #name {}
+
+        impl #registry::ShenyuRoute for #name {
+            fn uri_info() -> ::shenyu_client_rust::model::UriInfo {
+                ::shenyu_client_rust::model::UriInfo {
+                    path: #path.to_string(),
+                    rule_name: #rule_name.to_string(),
+                    service_name: #service_name,
+                    method_name: #method_name.to_string(),
+                    description: #desc.to_string(),
+                    parameter_types: #parameter_types.to_string(),
+                    rpc_ext: #rpc_ext.to_string(),
+                    plugin_names: ::std::vec![#(#plugin_names.to_string()),*],
+                    enabled: #enabled,
+                }
+            }
+        }
+
+        #registry::__private::inventory::submit! {
+            #registry::Registration::new(<#name as #registry::ShenyuRoute>::uri_info)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse::Parser;
+
+    fn expand_str(args: &str, item: &str) -> syn::Result<TokenStream2> {
+        let mut route = Route::default();
+        syn::meta::parser(|meta| route.parse(meta)).parse_str(args)?;
+        expand(route, syn::parse_str(item)?)
+    }
+
+    #[test]
+    fn test_expand() {
+        let tokens = expand_str(
+            r#"path = "/users", method = "POST", desc = "Creates a user", plugin_names = ["sign"]"#,
+            "pub async fn create_user() {}",
+        )
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("pub struct create_user { }"));
+        assert!(tokens.contains(r#"method_name : "post" . to_string ()"#));
+        assert!(tokens.contains(r#"rule_name : "/users" . to_string ()"#));
+        assert!(tokens.contains(r#"description : "Creates a user" . to_string ()"#));
+        assert!(tokens.contains(r#"plugin_names : :: std :: vec ! ["sign" . to_string ()]"#));
+        assert!(tokens.contains("enabled : true"));
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let err = |args: &str| {
+            expand_str(args, "async fn handler() {}")
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(err(r#"method = "get""#), "#[shenyu_client] requires a path");
+        assert_eq!(
+            err(r#"path = "/", method = "fetch""#),
+            "unknown method, expected one of get, post, put, delete, patch, head, options, trace"
+        );
+        assert!(err(r#"path = "/", method = "get", ruleName = "/""#)
+            .starts_with("unknown argument ruleName"));
+        assert_eq!(
+            err(r#"path = "/", method = "get", plugin_names = [1]"#),
+            "expected a string"
+        );
+    }
+}
@@ -0,0 +1,528 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! How the client authenticates to the ShenYu admin.
+//!
+//! The strategy is selected by `register.props.authType`, see [`from_config`].
+
+use crate::config::RegisterConfig;
+use crate::error::{Error, Result};
+use crate::registrar::http::PLATFORM_LOGIN_SUFFIX;
+use crate::registrar::{register_urls, Tries};
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, RequestBuilder};
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::env::VarError;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Adds credentials to the requests sent to the admin.
+#[async_trait]
+pub trait AuthStrategy: Debug + Send + Sync {
+    /// Obtains the credentials before anything is registered, e.g. logs in.
+    async fn login(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether the credentials should be renewed before the next request.
+    fn expiring(&self) -> bool {
+        false
+    }
+
+    /// Renews the credentials after the admin refused them, returning
+    /// whether the refused request is worth sending again.
+    async fn renew(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Adds the credentials to a request carrying `body`.
+    fn sign(&self, builder: RequestBuilder, body: &[u8]) -> Result<RequestBuilder>;
+}
+
+/// Creates the strategy matching `register.props.authType`: `token`
+/// (default), `bearer`, `hmac` or `none`.
+///
+/// `headers` are sent with every request of the registrar, the `token`
+/// strategy stores its `X-Access-Token` there.
+pub fn from_config(
+    config: &RegisterConfig,
+    http_client: reqwest::Client,
+    headers: Arc<DashMap<String, String>>,
+) -> Result<Box<dyn AuthStrategy>> {
+    match config.props.get("authType").map(String::as_str) {
+        None | Some("token") => Ok(Box::new(TokenLogin::new(config, http_client, headers))),
+        Some("bearer") => Ok(Box::new(BearerToken {
+            token: credential(&config.props, "token", |name| std::env::var(name).ok())?,
+        })),
+        Some("hmac") => Ok(Box::new(HmacSign {
+            app_key: credential(&config.props, "appKey", |name| std::env::var(name).ok())?,
+            app_secret: credential(&config.props, "appSecret", |name| std::env::var(name).ok())?,
+        })),
+        Some("none") => Ok(Box::new(NoAuth)),
+        Some(other) => Err(Error::Config(format!(
+            "register.props.authType: unsupported strategy {other}"
+        ))),
+    }
+}
+
+/// Reads the credential `key` from `props`, either as is, from the
+/// environment variable named by `{key}Env`, looked up with `env`, or from
+/// the file named by `{key}File`.
+pub(crate) fn credential(
+    props: &HashMap<String, String>,
+    key: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<String> {
+    if let Some(path) = props.get(&format!("{key}File")) {
+        return std::fs::read_to_string(path)
+            .map(|value| value.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|e| Error::Config(format!("register.props.{key}File ({path}): {e}")));
+    }
+    if let Some(var) = props.get(&format!("{key}Env")) {
+        return env(var).ok_or_else(|| {
+            Error::Config(format!(
+                "register.props.{key}Env ({var}): {}",
+                VarError::NotPresent
+            ))
+        });
+    }
+    props
+        .get(key)
+        .cloned()
+        .ok_or_else(|| Error::Config(format!("register.props.{key} is required to log in")))
+}
+
+/// Logs in to `/platform/login` with a username and password, and registers
+/// with the `X-Access-Token` it answers.
+///
+/// Supported `register.props`:
+///
+/// * `username` and `password`, each also readable through `{key}Env` or
+///   `{key}File`, see [`from_config`];
+/// * `loginMethod`: `get` (default) sends the credentials as query
+///   parameters, which puts the password in the URL, and so in the access
+///   logs of the admin and of any proxy; `post` sends them as a JSON body
+///   instead, for admins accepting it;
+/// * `passwordHash`: `sha512` sends the hex SHA-512 of the password instead
+///   of the password itself;
+/// * `tokenRefreshAhead` in seconds to log in again that long before the
+///   token expires, as told by its `exp` claim.
+#[derive(Debug)]
+pub struct TokenLogin {
+    http_client: reqwest::Client,
+    headers: Arc<DashMap<String, String>>,
+    props: HashMap<String, String>,
+    login_urls: Vec<String>,
+    refresh_ahead: Option<Duration>,
+    expiry: Mutex<Option<SystemTime>>,
+}
+
+impl TokenLogin {
+    pub fn new(
+        config: &RegisterConfig,
+        http_client: reqwest::Client,
+        headers: Arc<DashMap<String, String>>,
+    ) -> Self {
+        TokenLogin {
+            http_client,
+            headers,
+            props: config.props.clone(),
+            login_urls: register_urls(config)
+                .into_iter()
+                .map(|url| format!("{}{}", url, PLATFORM_LOGIN_SUFFIX))
+                .collect(),
+            refresh_ahead: config
+                .props
+                .get("tokenRefreshAhead")
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs),
+            expiry: Mutex::new(None),
+        }
+    }
+
+    /// The `userName` and `password` to log in with.
+    fn login_params(&self) -> Result<[(&'static str, String); 2]> {
+        let username = credential(&self.props, "username", |name| std::env::var(name).ok())?;
+        let password = credential(&self.props, "password", |name| std::env::var(name).ok())?;
+        let password = match self.props.get("passwordHash").map(String::as_str) {
+            None | Some("none") => password,
+            Some("sha512") => hex(&Sha512::digest(password.as_bytes())),
+            Some(other) => {
+                return Err(Error::Config(format!(
+                    "register.props.passwordHash: unsupported hash {other}"
+                )))
+            }
+        };
+        Ok([("userName", username), ("password", password)])
+    }
+
+    /// GET, with the credentials in the URL, unless `loginMethod` is `post`.
+    fn login_method(&self) -> Result<Method> {
+        match self.props.get("loginMethod").map(String::as_str) {
+            None | Some("get") => Ok(Method::GET),
+            Some("post") => Ok(Method::POST),
+            Some(other) => Err(Error::Config(format!(
+                "register.props.loginMethod: unsupported method {other}"
+            ))),
+        }
+    }
+
+    /// Logs in to the admin and returns the `X-Access-Token` to register with.
+    pub async fn get_register_token(&self) -> Result<String> {
+        let params = self.login_params()?;
+        let method = self.login_method()?;
+
+        let mut attempts = Vec::new();
+        let mut tries = Tries::new(&mut attempts);
+        for url in &self.login_urls {
+            let started = Instant::now();
+            let builder = if method == Method::POST {
+                self.http_client
+                    .post(url)
+                    .json(&HashMap::from(params.clone()))
+            } else {
+                self.http_client.get(url).query(&params)
+            };
+            let res = match builder.send().await {
+                Ok(res) => res,
+                Err(e) => {
+                    tries.failed(url, Error::transport(url, e), started);
+                    continue;
+                }
+            };
+            let status = res.status();
+            let body = match res.text().await {
+                Ok(body) => body,
+                Err(e) => {
+                    tries.failed(url, Error::transport(url, e), started);
+                    continue;
+                }
+            };
+            // an admin still starting up may answer with anything, so a body
+            // without a token is a failure of this server only
+            let token = serde_json::from_str::<Value>(&body).ok().and_then(|res| {
+                res.get("data")
+                    .and_then(|data| data.get("token"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            });
+            match token {
+                Some(token) => return Ok(token),
+                None if status.is_success() => tries.failed(
+                    url,
+                    Error::Auth(format!("{url}: no token in response: {body}")),
+                    started,
+                ),
+                None => tries.failed(
+                    url,
+                    Error::Rejected {
+                        status: status.as_u16(),
+                        body,
+                    },
+                    started,
+                ),
+            }
+        }
+        Err(tries.into_error())
+    }
+}
+
+#[async_trait]
+impl AuthStrategy for TokenLogin {
+    async fn login(&self) -> Result<()> {
+        let token = self.get_register_token().await?;
+        *self.expiry.lock().unwrap_or_else(PoisonError::into_inner) = jwt_expiry(&token);
+        self.headers.insert("X-Access-Token".to_string(), token);
+        Ok(())
+    }
+
+    fn expiring(&self) -> bool {
+        let Some(ahead) = self.refresh_ahead else {
+            return false;
+        };
+        self.expiry
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some_and(|expiry| SystemTime::now() + ahead >= expiry)
+    }
+
+    async fn renew(&self) -> Result<bool> {
+        self.login().await?;
+        Ok(true)
+    }
+
+    /// The token is sent with the registrar's headers.
+    fn sign(&self, builder: RequestBuilder, _body: &[u8]) -> Result<RequestBuilder> {
+        Ok(builder)
+    }
+}
+
+/// Sends a fixed `Authorization: Bearer` token, read from the `token` prop
+/// (or `tokenEnv`/`tokenFile`).
+#[derive(Debug)]
+pub struct BearerToken {
+    token: String,
+}
+
+impl AuthStrategy for BearerToken {
+    fn sign(&self, builder: RequestBuilder, _body: &[u8]) -> Result<RequestBuilder> {
+        Ok(builder.bearer_auth(&self.token))
+    }
+}
+
+/// Signs every request with the `appKey`/`appSecret` props (each also
+/// readable through `{key}Env` or `{key}File`).
+///
+/// Requests carry the `appKey`, the `timestamp` in milliseconds and the
+/// `sign`: the hex HMAC-SHA256 of the timestamp followed by the body, keyed
+/// with the secret.
+#[derive(Debug)]
+pub struct HmacSign {
+    app_key: String,
+    app_secret: String,
+}
+
+impl HmacSign {
+    fn signature(&self, timestamp: &str, body: &[u8]) -> Result<String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.app_secret.as_bytes())
+            .map_err(|e| Error::Config(format!("register.props.appSecret: {e}")))?;
+        mac.update(timestamp.as_bytes());
+        mac.update(body);
+        Ok(hex(&mac.finalize().into_bytes()))
+    }
+}
+
+impl AuthStrategy for HmacSign {
+    fn sign(&self, builder: RequestBuilder, body: &[u8]) -> Result<RequestBuilder> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+            .to_string();
+        let sign = self.signature(&timestamp, body)?;
+        Ok(builder
+            .header("appKey", &self.app_key)
+            .header("timestamp", timestamp)
+            .header("sign", sign))
+    }
+}
+
+/// Sends no credentials, for admins that don't protect the register
+/// endpoints.
+#[derive(Debug)]
+pub struct NoAuth;
+
+impl AuthStrategy for NoAuth {
+    fn sign(&self, builder: RequestBuilder, _body: &[u8]) -> Result<RequestBuilder> {
+        Ok(builder)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The `exp` claim of a JWT, `None` if `token` isn't one or has no expiry.
+fn jwt_expiry(token: &str) -> Option<SystemTime> {
+    let payload = token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let exp = serde_json::from_slice::<Value>(&payload).ok()?["exp"].as_u64()?;
+    Some(UNIX_EPOCH + Duration::from_secs(exp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ShenyuClient;
+    use crate::model::UriInfo;
+    use crate::registrar::http::REGISTER_URI_SUFFIX;
+    use crate::test_support::{config, FakeServer};
+
+    fn register_config(admin: &FakeServer, props: &[(&str, &str)]) -> RegisterConfig {
+        let mut config = config(&admin.base_url).register;
+        for (key, value) in props {
+            config.props.insert(key.to_string(), value.to_string());
+        }
+        config
+    }
+
+    fn token_login(admin: &FakeServer, props: &[(&str, &str)]) -> TokenLogin {
+        TokenLogin::new(
+            &register_config(admin, props),
+            reqwest::Client::new(),
+            Arc::default(),
+        )
+    }
+
+    fn client(admin: &FakeServer, props: &[(&str, &str)]) -> ShenyuClient {
+        let mut config = config(&admin.base_url);
+        config.register = register_config(admin, props);
+        let uri_infos = vec![UriInfo::new("/health", "get")];
+        ShenyuClient::builder()
+            .config(config)
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_jwt_expiry() {
+        let claims = URL_SAFE_NO_PAD.encode(r#"{"userName":"admin","exp":1700000000}"#);
+        let token = format!("eyJhbGciOiJIUzI1NiJ9.{claims}.c2lnbmF0dXJl");
+        let exp = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(jwt_expiry(&token), Some(exp));
+        assert_eq!(jwt_expiry("fake-token"), None);
+        assert_eq!(jwt_expiry("a.bm90IGpzb24.c"), None);
+    }
+
+    #[tokio::test]
+    async fn test_post_login_with_hashed_password() {
+        let admin = FakeServer::admin();
+        let login = token_login(
+            &admin,
+            &[("loginMethod", "post"), ("passwordHash", "sha512")],
+        );
+        assert_eq!(login.get_register_token().await.unwrap(), "fake-token");
+
+        let request = &admin.requests_to(PLATFORM_LOGIN_SUFFIX)[0];
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.uri.query(), None);
+        assert_eq!(request.json()["userName"], "admin");
+        assert_eq!(
+            request.json()["password"],
+            "ba3253876aed6bc22d4a6ff53d8406c6ad864195ed144ab5c87621b6c233b548\
+             baeae6956df346ec8c17f5ea10f35ee3cbc514797ed7ddd3145464e2a0bab413"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_login_urls() {
+        let admin = FakeServer::admin();
+        let host_port = admin.base_url.trim_start_matches("http://");
+        let mut config = register_config(&admin, &[("username", "admin"), ("password", "123")]);
+        config.servers = format!("{}/, {host_port},", admin.base_url);
+        let login = TokenLogin::new(&config, reqwest::Client::new(), Arc::default());
+        let url = format!("{}{PLATFORM_LOGIN_SUFFIX}", admin.base_url);
+        assert_eq!(login.login_urls, [url.clone(), url]);
+
+        login.get_register_token().await.unwrap();
+        assert_eq!(admin.requests_to(PLATFORM_LOGIN_SUFFIX).len(), 1);
+    }
+
+    #[test]
+    fn test_credential_from_env() {
+        let env = |name: &str| (name == "ADMIN_USER").then(|| "operator".to_string());
+        let props: HashMap<_, _> = [("usernameEnv".to_string(), "ADMIN_USER".to_string())].into();
+        assert_eq!(credential(&props, "username", env).unwrap(), "operator");
+
+        let props: HashMap<_, _> = [("usernameEnv".to_string(), "UNSET".to_string())].into();
+        let err = credential(&props, "username", env).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.props.usernameEnv (UNSET): environment variable not found"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_credentials_from_file() {
+        let admin = FakeServer::admin();
+        let file = std::env::temp_dir().join(format!("shenyu-password-{}", std::process::id()));
+        std::fs::write(&file, "s3cret\n").unwrap();
+        let login = token_login(
+            &admin,
+            &[
+                ("username", "operator"),
+                ("passwordFile", file.to_str().unwrap()),
+            ],
+        );
+        login.get_register_token().await.unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        let params = admin.requests_to(PLATFORM_LOGIN_SUFFIX)[0].params();
+        assert_eq!(params["userName"], "operator");
+        assert_eq!(params["password"], "s3cret");
+
+        let login = token_login(&admin, &[("passwordFile", "/nonexistent/password")]);
+        let err = login.get_register_token().await.unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+        assert!(err.to_string().contains("passwordFile"));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let admin = FakeServer::admin();
+        let strategy = |props: &[(&str, &str)]| {
+            from_config(
+                &register_config(&admin, props),
+                reqwest::Client::new(),
+                Arc::default(),
+            )
+        };
+        assert!(strategy(&[]).is_ok());
+        assert!(strategy(&[("authType", "none")]).is_ok());
+        let err = strategy(&[("authType", "bearer")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.props.token is required to log in"
+        );
+        let err = strategy(&[("authType", "kerberos")]).unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+    }
+
+    #[tokio::test]
+    async fn test_hmac_sign() {
+        let admin = FakeServer::admin();
+        let client = client(
+            &admin,
+            &[
+                ("authType", "hmac"),
+                ("appKey", "app-key"),
+                ("appSecret", "app-secret"),
+            ],
+        );
+        client.register_async().await.unwrap();
+        assert!(admin.requests_to(PLATFORM_LOGIN_SUFFIX).is_empty());
+
+        let uri = &admin.requests_to(REGISTER_URI_SUFFIX)[0];
+        assert_eq!(uri.headers["appKey"], "app-key");
+        assert!(uri.headers.get("X-Access-Token").is_none());
+        let timestamp = uri.headers["timestamp"].to_str().unwrap();
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"app-secret").unwrap();
+        mac.update(format!("{}{}", timestamp, uri.body).as_bytes());
+        assert_eq!(uri.headers["sign"], hex(&mac.finalize().into_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token() {
+        let admin = FakeServer::admin();
+        let client = client(&admin, &[("authType", "bearer"), ("token", "static")]);
+        client.register_uri_async().await.unwrap();
+        client.offline_register_async().await.unwrap();
+        for request in admin.requests() {
+            assert_eq!(request.headers["Authorization"], "Bearer static");
+        }
+    }
+}
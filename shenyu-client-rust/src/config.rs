@@ -15,60 +15,319 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod loader;
+mod spring;
+mod validate;
+
+pub use loader::{ConfigLoader, Format};
+
+use crate::error::{Error, Result};
+use ipnet::IpNet;
 use serde::Deserialize;
-use serde_yaml;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
+use std::str::FromStr;
 
 #[derive(Debug, Deserialize)]
 pub struct EnvConfig {
     pub(crate) shenyu: ShenYuConfig,
 }
 
+/// The `shenyu` section of `config.yml`. Only `register.servers` and
+/// `uri.app_name` have no default; [`ShenYuConfig::validate`] tells what is
+/// missing or wrong.
 #[derive(Debug, Deserialize)]
 pub struct ShenYuConfig {
     pub register: RegisterConfig,
+    #[serde(default)]
     pub uri: UriConfig,
-    pub discovery: DiscoveryConfig,
+    /// Registered to the admin when set, see [`DiscoveryConfig`].
+    #[serde(default)]
+    pub discovery: Option<DiscoveryConfig>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
 }
 
 impl ShenYuConfig {
-    pub fn from_yaml_file(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let current_dir = std::env::current_dir().unwrap();
-
-        let mut file = File::open(current_dir.join(file_path))?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        let config: EnvConfig = serde_yaml::from_str(&contents)?;
-        Ok(config.shenyu)
+    /// Reads the config file at `file_path`, absolute or relative to the
+    /// current directory, see [`ConfigLoader::file`].
+    pub fn from_yaml_file(file_path: &str) -> Result<Self> {
+        Self::loader().file(file_path).load()
+    }
+
+    /// Loads the config from a file, the environment and overrides, see
+    /// [`ConfigLoader`].
+    pub fn loader() -> ConfigLoader {
+        ConfigLoader::default()
+    }
+}
+
+impl FromStr for ShenYuConfig {
+    type Err = Error;
+
+    /// Parses the contents of a `config.yml`.
+    fn from_str(yaml: &str) -> Result<Self> {
+        Self::loader().yaml(yaml).load()
     }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RegisterConfig {
+    /// `http` when not set.
+    #[serde(default = "default_register_type")]
     pub register_type: String,
+    #[serde(default)]
     pub servers: String,
+    #[serde(default)]
     pub props: HashMap<String, String>,
+    /// Re-registers periodically when set, see [`HeartbeatConfig`].
+    #[serde(default)]
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// TLS settings of the connections to `servers`, see [`TlsConfig`].
+    #[serde(default)]
+    pub tls: TlsConfig,
+}
+
+/// TLS settings of the connections to the register center, for servers
+/// using `https://` URLs.
+///
+/// ```yaml
+/// shenyu:
+///   register:
+///     tls:
+///       ca_file: /etc/shenyu/ca.pem
+///       cert_file: /etc/shenyu/client.pem
+///       key_file: /etc/shenyu/client.key
+///       server_name: admin.internal
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// PEM bundle of the CAs trusted besides the built-in roots.
+    pub ca_file: Option<String>,
+    /// PEM certificate chain presented to servers requiring client
+    /// certificates, along with `key_file`.
+    pub cert_file: Option<String>,
+    /// PEM private key of `cert_file`.
+    pub key_file: Option<String>,
+    /// The name sent as SNI and checked against the server certificate of
+    /// the single server of `servers`, addressed by IP.
+    pub server_name: Option<String>,
+    /// Accepts any server certificate. For development only.
+    pub insecure_skip_verify: bool,
+}
+
+/// Periodic re-registration, so routes come back by themselves after the
+/// register center restarts or loses its state.
+///
+/// ```yaml
+/// shenyu:
+///   register:
+///     heartbeat:
+///       interval_ms: 30000
+///       jitter_ms: 3000
+///       metadata: true
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HeartbeatConfig {
+    /// Delay between two rounds, in milliseconds.
+    pub interval_ms: u64,
+    /// Random delay of up to this many milliseconds added to every round,
+    /// so instances started together don't hit the register center at once.
+    pub jitter_ms: u64,
+    /// Whether the metadata is re-registered too, not only the URI.
+    pub metadata: bool,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig {
+            interval_ms: 30_000,
+            jitter_ms: 3_000,
+            metadata: false,
+        }
+    }
+}
+
+/// How calls to the register center are retried when all its servers failed.
+///
+/// ```yaml
+/// shenyu:
+///   retry:
+///     max_attempts: 5
+///     base_delay_ms: 500
+///     max_delay_ms: 5000
+///     jitter_ms: 100
+///     retry_on: [unreachable, refused]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Attempts in total, including the first one; `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every following one.
+    pub base_delay_ms: u64,
+    /// Upper bound of the doubled delay.
+    pub max_delay_ms: u64,
+    /// Random delay of up to this many milliseconds added to every retry.
+    pub jitter_ms: u64,
+    /// The failures worth retrying.
+    pub retry_on: Vec<RetryOn>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 5_000,
+            jitter_ms: 100,
+            retry_on: vec![RetryOn::Unreachable, RetryOn::Refused],
+        }
+    }
+}
+
+/// A kind of failure [`RetryConfig`] may retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryOn {
+    /// No server of the register center could be reached.
+    Unreachable,
+    /// The register center answered but refused the call, e.g. while it is
+    /// still starting up.
+    Refused,
+}
+
+/// The HTTP client shared by every call to the register center.
+///
+/// ```yaml
+/// shenyu:
+///   http:
+///     connect_timeout_ms: 3000
+///     read_timeout_ms: 10000
+///     proxy: http://proxy.internal:3128
+///     no_proxy: localhost,.internal
+///     pool_max_idle_per_host: 4
+///     user_agent: my-service/1.0
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// How long connecting to a server may take, in milliseconds.
+    pub connect_timeout_ms: u64,
+    /// How long a server may stay silent while answering, in milliseconds.
+    pub read_timeout_ms: u64,
+    /// Proxy of every call, e.g. `http://proxy:3128`; the `HTTP_PROXY`
+    /// family of environment variables is used when unset.
+    pub proxy: Option<String>,
+    /// Comma-separated hosts reached without `proxy`.
+    pub no_proxy: Option<String>,
+    /// Idle connections kept open per server.
+    pub pool_max_idle_per_host: usize,
+    /// How long an idle connection is kept open, in milliseconds.
+    pub pool_idle_timeout_ms: u64,
+    pub user_agent: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout_ms: 3_000,
+            read_timeout_ms: 10_000,
+            proxy: None,
+            no_proxy: None,
+            pool_max_idle_per_host: 8,
+            pool_idle_timeout_ms: 90_000,
+            user_agent: concat!("shenyu-client-rust/", env!("CARGO_PKG_VERSION")).to_string(),
+        }
+    }
+}
+
+fn default_register_type() -> String {
+    "http".to_string()
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(default)]
 pub struct UriConfig {
     pub app_name: String,
-    pub host: String,
-    pub port: u16,
+    /// The address advertised to the register center, picked from the
+    /// network interfaces as told by `network` when not set.
+    pub host: Option<String>,
+    /// The port advertised to the register center, the one the client is
+    /// built with when not set.
+    pub port: Option<u16>,
+    /// The app name is used as the context node when empty.
     pub context_path: String,
     pub environment: String,
+    /// `http` when not set.
     pub rpc_type: String,
+    /// How the address to advertise is picked, see [`NetworkConfig`].
+    pub network: NetworkConfig,
 }
 
+impl Default for UriConfig {
+    fn default() -> Self {
+        UriConfig {
+            app_name: String::new(),
+            host: None,
+            port: None,
+            context_path: String::new(),
+            environment: String::new(),
+            rpc_type: "http".to_string(),
+            network: NetworkConfig::default(),
+        }
+    }
+}
+
+/// How the address to advertise is picked among those of the network
+/// interfaces, when `uri.host` isn't set. Loopback, link-local and multicast
+/// addresses are skipped, unless `interface` names them.
+///
+/// ```yaml
+/// shenyu:
+///   uri:
+///     network:
+///       interface: eth0
+///       allow: ["10.0.0.0/8", "fd00::/8"]
+///       deny: ["10.96.0.0/12"]
+///       prefer: ipv6
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Only the addresses of the interface with this name.
+    pub interface: Option<String>,
+    /// Only the addresses in one of these networks, when not empty.
+    pub allow: Vec<IpNet>,
+    /// None of the addresses in these networks.
+    pub deny: Vec<IpNet>,
+    /// The family tried first, the other one being the fallback.
+    pub prefer: IpFamily,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    #[default]
+    Ipv4,
+    Ipv6,
+}
+
+/// Where the admin discovers the instances of the app, registered as a
+/// discovery config of its selector.
 #[derive(Debug, Deserialize)]
 pub struct DiscoveryConfig {
+    #[serde(default)]
     pub protocol: String,
     pub discovery_type: String,
     pub server_lists: String,
     pub register_path: String,
+    #[serde(default)]
     pub plugin_name: String,
+    #[serde(default)]
     pub props: HashMap<String, String>,
 }
 
@@ -87,4 +346,51 @@ mod tests {
         assert_eq!(config.register.servers, "http://127.0.0.1:9095");
         assert_eq!(config.register.props.len(), 2);
     }
+
+    #[test]
+    fn test_defaults() {
+        let config: ShenYuConfig = "shenyu:\n  register:\n    servers: http://127.0.0.1:9095\n    props: {username: admin, password: \"123456\"}\n  uri:\n    app_name: app1\n"
+            .parse()
+            .unwrap();
+        assert_eq!(config.register.register_type, "http");
+        assert_eq!(config.uri.rpc_type, "http");
+        assert_eq!(config.uri.context_path, "");
+        assert!(config.discovery.is_none());
+    }
+
+    #[test]
+    fn test_heartbeat_defaults() {
+        let config: RegisterConfig = serde_yaml::from_str(
+            "register_type: http\nservers: http://127.0.0.1:9095\nprops: {}\nheartbeat:\n  metadata: true\n",
+        )
+        .unwrap();
+        let heartbeat = config.heartbeat.unwrap();
+        assert_eq!(heartbeat.interval_ms, 30_000);
+        assert_eq!(heartbeat.jitter_ms, 3_000);
+        assert!(heartbeat.metadata);
+    }
+
+    #[test]
+    fn test_heartbeat_disabled() {
+        let config: RegisterConfig =
+            serde_yaml::from_str("register_type: http\nservers: http://127.0.0.1:9095\n").unwrap();
+        assert_eq!(config.heartbeat, None);
+    }
+
+    #[test]
+    fn test_retry_defaults() {
+        let config: ShenYuConfig = "shenyu:\n  register:\n    servers: http://127.0.0.1:9095\n    props: {username: admin, password: \"123456\"}\n  uri:\n    app_name: app1\n"
+            .parse()
+            .unwrap();
+        assert_eq!(config.retry, RetryConfig::default());
+    }
+
+    #[test]
+    fn test_retry() {
+        let config: RetryConfig =
+            serde_yaml::from_str("max_attempts: 5\nretry_on: [unreachable]\n").unwrap();
+        assert_eq!(config.max_attempts, 5);
+        assert_eq!(config.base_delay_ms, 500);
+        assert_eq!(config.retry_on, [RetryOn::Unreachable]);
+    }
 }
@@ -0,0 +1,562 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Layered loading of [`ShenYuConfig`], see [`ConfigLoader`].
+
+use super::{spring, EnvConfig, ShenYuConfig};
+use crate::error::{Error, Result};
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+
+/// The nested sections of the config, by path; everything else is a value.
+const SECTIONS: &[&str] = &[
+    "register",
+    "register.props",
+    "register.heartbeat",
+    "register.tls",
+    "uri",
+    "uri.network",
+    "discovery",
+    "discovery.props",
+    "retry",
+    "http",
+];
+
+/// The sections holding free-form camelCase keys rather than fields.
+const PROPS: &[&str] = &["register.props", "discovery.props"];
+
+/// Loads a [`ShenYuConfig`] from layers, each overriding the previous ones:
+///
+/// 1. a file or string in one of the [`Format`]s, with the `shenyu:` root of
+///    `config.yml`, or the `shenyu.register.*`/`shenyu.client.*` layout of
+///    the Java client's `application.yml`;
+/// 2. environment variables, when enabled with [`ConfigLoader::env`];
+/// 3. values set with [`ConfigLoader::set`].
+///
+/// The result is checked with [`ShenYuConfig::validate`].
+///
+/// Environment variables are named after the path of the value they set:
+/// `SHENYU_REGISTER_SERVERS` sets `register.servers`, `SHENYU_URI_PORT` sets
+/// `uri.port`, and `SHENYU_REGISTER_PROPS_PASSWORD` sets the `password` prop.
+/// camelCase props are spelled in snake case, e.g.
+/// `SHENYU_REGISTER_PROPS_TOKEN_REFRESH_AHEAD` for `tokenRefreshAhead`.
+///
+/// ```no_run
+/// use shenyu_client_rust::config::ShenYuConfig;
+///
+/// let config = ShenYuConfig::loader()
+///     .file("/etc/shenyu/config.yml")
+///     .env()
+///     .set("uri.port", "8080")
+///     .load()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct ConfigLoader {
+    source: Option<Source>,
+    env_prefix: Option<String>,
+    overrides: Vec<(String, String)>,
+}
+
+/// The formats a config can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+    /// Flattened `key=value` lines, as in Spring's `application.properties`.
+    Properties,
+}
+
+impl Format {
+    /// The format of the file at `path` by its extension, YAML when unknown.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Format::Toml,
+            Some("json") => Format::Json,
+            Some("properties") => Format::Properties,
+            _ => Format::Yaml,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Source {
+    File(PathBuf),
+    Text(String, Format),
+}
+
+impl ConfigLoader {
+    /// Reads the file at `path`, absolute or relative to the current
+    /// directory, in the format of its extension, see [`Format::from_path`].
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.source = Some(Source::File(path.into()));
+        self
+    }
+
+    /// Reads `yaml` instead of a file.
+    pub fn yaml(self, yaml: impl Into<String>) -> Self {
+        self.text(yaml, Format::Yaml)
+    }
+
+    /// Reads `text`, written in `format`, instead of a file.
+    pub fn text(mut self, text: impl Into<String>, format: Format) -> Self {
+        self.source = Some(Source::Text(text.into(), format));
+        self
+    }
+
+    /// Lets `SHENYU_*` environment variables override the file.
+    pub fn env(self) -> Self {
+        self.env_prefix("SHENYU")
+    }
+
+    /// Lets `{prefix}_*` environment variables override the file.
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets the value at the dotted `key`, e.g. `register.servers`,
+    /// overriding the file and the environment.
+    pub fn set(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.overrides.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn load(self) -> Result<ShenYuConfig> {
+        self.load_env(std::env::vars())
+    }
+
+    /// Loads the config, with `vars` as the environment.
+    fn load_env(self, vars: impl Iterator<Item = (String, String)>) -> Result<ShenYuConfig> {
+        let (mut root, origin) = match &self.source {
+            Some(Source::File(path)) => {
+                let origin = path.display().to_string();
+                let contents = std::fs::read_to_string(path)
+                    .map_err(|e| Error::Config(format!("{origin}: {e}")))?;
+                (parse(&contents, Format::from_path(path), &origin)?, origin)
+            }
+            Some(Source::Text(text, format)) => {
+                let origin = format!("{format:?}").to_lowercase();
+                (parse(text, *format, &origin)?, origin)
+            }
+            None => (Value::Mapping(Mapping::new()), "config".to_string()),
+        };
+        if spring::is_spring(&root) {
+            spring::to_native(&mut root);
+        }
+        if let Some(prefix) = &self.env_prefix {
+            apply_env(&mut root, prefix, vars);
+        }
+        for (key, value) in &self.overrides {
+            let path: Vec<String> = std::iter::once("shenyu")
+                .chain(key.split('.'))
+                .map(str::to_string)
+                .collect();
+            set(&mut root, &path, value);
+        }
+        let config = into_config(&root, &origin)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// `text` as a YAML value, whatever its format.
+fn parse(text: &str, format: Format, origin: &str) -> Result<Value> {
+    let invalid = |e: &dyn std::fmt::Display| Error::Config(format!("{origin}: {e}"));
+    match format {
+        Format::Yaml => serde_yaml::from_str(text).map_err(|e| invalid(&e)),
+        Format::Toml => toml::from_str::<toml::Value>(text)
+            .map_err(|e| invalid(&e))
+            .and_then(|value| serde_yaml::to_value(value).map_err(|e| invalid(&e))),
+        Format::Json => serde_json::from_str::<serde_json::Value>(text)
+            .map_err(|e| invalid(&e))
+            .and_then(|value| serde_yaml::to_value(value).map_err(|e| invalid(&e))),
+        Format::Properties => Ok(properties(text)),
+    }
+}
+
+/// The `key=value` (or `key: value`) lines of `text` as nested mappings,
+/// skipping `#` and `!` comments and joining lines ending with `\`.
+fn properties(text: &str) -> Value {
+    let mut root = Value::Mapping(Mapping::new());
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let mut line = line.trim_start().to_string();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some(next) => line.push_str(next.trim_start()),
+                None => break,
+            }
+        }
+        let (key, value) = match line.find(['=', ':']) {
+            Some(at) => (&line[..at], &line[at + 1..]),
+            None => (line.as_str(), ""),
+        };
+        let path: Vec<String> = key.trim().split('.').map(str::to_string).collect();
+        set(&mut root, &path, value.trim());
+    }
+    root
+}
+
+fn into_config(root: &Value, origin: &str) -> Result<ShenYuConfig> {
+    // through text, so that numbers set from strings fill string fields and
+    // the other way round, as in a file
+    let yaml = serde_yaml::to_string(root).map_err(|e| Error::Config(format!("{origin}: {e}")))?;
+    serde_yaml::from_str::<EnvConfig>(&yaml)
+        .map(|config| config.shenyu)
+        .map_err(|e| Error::Config(format!("{origin}: {e}")))
+}
+
+/// Sets the value of every `{prefix}_*` variable of `vars` in `root`.
+fn apply_env(root: &mut Value, prefix: &str, vars: impl Iterator<Item = (String, String)>) {
+    let prefix = format!("{}_", prefix.to_uppercase());
+    for (name, value) in vars {
+        if let Some(rest) = name.strip_prefix(&prefix) {
+            let path = env_path(root, rest);
+            set(root, &path, &value);
+        }
+    }
+}
+
+/// The path of the value set by the variable named `SHENYU_{rest}`.
+fn env_path(root: &Value, rest: &str) -> Vec<String> {
+    let mut path = vec!["shenyu".to_string()];
+    let mut section = String::new();
+    let mut rest = rest;
+    loop {
+        let child = SECTIONS.iter().find_map(|candidate| {
+            let name = match section.as_str() {
+                "" => candidate.strip_prefix(""),
+                parent => candidate.strip_prefix(&format!("{parent}.")),
+            }
+            .filter(|name| !name.contains('.'))?;
+            rest.strip_prefix(&name.to_uppercase())?
+                .strip_prefix('_')
+                .map(|rest| (*candidate, name, rest))
+        });
+        match child {
+            Some((candidate, name, remaining)) => {
+                path.push(name.to_string());
+                section = candidate.to_string();
+                rest = remaining;
+            }
+            None if PROPS.contains(&section.as_str()) => {
+                let existing = get(root, &path)
+                    .and_then(Value::as_mapping)
+                    .and_then(|props| {
+                        props
+                            .keys()
+                            .filter_map(Value::as_str)
+                            .find(|key| screaming_snake(key) == rest)
+                    });
+                path.push(existing.map_or_else(|| camel(rest), str::to_string));
+                return path;
+            }
+            None => {
+                path.push(rest.to_lowercase());
+                return path;
+            }
+        }
+    }
+}
+
+fn get<'a>(root: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(root, |node, key| node.get(key.as_str()))
+}
+
+/// Sets `value` at `path`, creating the missing sections.
+fn set(root: &mut Value, path: &[String], value: &str) {
+    let Some((leaf, sections)) = path.split_last() else {
+        return;
+    };
+    let mut node = root;
+    for key in sections {
+        if !node.is_mapping() {
+            *node = Value::Mapping(Mapping::new());
+        }
+        node = match node {
+            Value::Mapping(map) => map.entry(Value::String(key.clone())).or_insert(Value::Null),
+            _ => unreachable!(),
+        };
+    }
+    if !node.is_mapping() {
+        *node = Value::Mapping(Mapping::new());
+    }
+    if let Value::Mapping(map) = node {
+        map.insert(Value::String(leaf.clone()), scalar(value));
+    }
+}
+
+/// `value` as a number or boolean when it reads as one exactly, as a string
+/// otherwise, e.g. `007` or `1e3` stay strings.
+fn scalar(value: &str) -> Value {
+    match serde_yaml::from_str::<Value>(value) {
+        Ok(typed @ (Value::Bool(_) | Value::Number(_)))
+            if serde_yaml::to_string(&typed).is_ok_and(|text| text.trim_end() == value) =>
+        {
+            typed
+        }
+        _ => Value::String(value.to_string()),
+    }
+}
+
+/// `tokenRefreshAhead` to `TOKEN_REFRESH_AHEAD`.
+fn screaming_snake(key: &str) -> String {
+    let mut name = String::new();
+    for c in key.chars() {
+        if c.is_uppercase() && !name.is_empty() {
+            name.push('_');
+        }
+        name.push(c.to_ascii_uppercase());
+    }
+    name
+}
+
+/// `TOKEN_REFRESH_AHEAD` to `tokenRefreshAhead`.
+fn camel(name: &str) -> String {
+    let mut key = String::new();
+    for (i, word) in name.split('_').filter(|w| !w.is_empty()).enumerate() {
+        let word = word.to_lowercase();
+        match i {
+            0 => key.push_str(&word),
+            _ => {
+                let mut chars = word.chars();
+                key.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+                key.push_str(chars.as_str());
+            }
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HeartbeatConfig;
+    use crate::test_support::config_yaml;
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let mut root = parse(&config_yaml("http://127.0.0.1:9095"), Format::Yaml, "yaml").unwrap();
+        apply_env(
+            &mut root,
+            "SHENYU",
+            vars(&[
+                (
+                    "SHENYU_REGISTER_SERVERS",
+                    "http://admin-0:9095,http://admin-1:9095",
+                ),
+                ("SHENYU_REGISTER_PROPS_PASSWORD", "123456"),
+                ("SHENYU_REGISTER_PROPS_TOKEN_REFRESH_AHEAD", "60"),
+                ("SHENYU_REGISTER_HEARTBEAT_INTERVAL_MS", "1000"),
+                ("SHENYU_DISCOVERY_PROPS_MAX_RETRIES", "9"),
+                ("SHENYU_URI_PORT", "9000"),
+                ("SHENYU_RETRY_MAX_ATTEMPTS", "5"),
+                ("OTHER_URI_PORT", "1"),
+            ]),
+        );
+        let config = into_config(&root, "yaml").unwrap();
+        assert_eq!(
+            config.register.servers,
+            "http://admin-0:9095,http://admin-1:9095"
+        );
+        assert_eq!(config.register.props["password"], "123456");
+        assert_eq!(config.register.props["tokenRefreshAhead"], "60");
+        assert_eq!(
+            config.register.heartbeat,
+            Some(HeartbeatConfig {
+                interval_ms: 1000,
+                ..Default::default()
+            })
+        );
+        assert_eq!(config.discovery.as_ref().unwrap().props["maxRetries"], "9");
+        assert_eq!(config.discovery.as_ref().unwrap().props.len(), 1);
+        assert_eq!(config.uri.port, Some(9000));
+        assert_eq!(config.retry.max_attempts, 5);
+    }
+
+    #[test]
+    fn test_env_advertised_address() {
+        let mut root = parse(&config_yaml("http://127.0.0.1:9095"), Format::Yaml, "yaml").unwrap();
+        apply_env(
+            &mut root,
+            "SHENYU",
+            vars(&[
+                ("SHENYU_URI_HOST", "pod.internal"),
+                ("SHENYU_URI_PORT", "9000"),
+            ]),
+        );
+        let config = into_config(&root, "yaml").unwrap();
+        assert_eq!(config.uri.host.as_deref(), Some("pod.internal"));
+        assert_eq!(config.uri.port, Some(9000));
+    }
+
+    #[test]
+    fn test_overrides_win() {
+        let config = ShenYuConfig::loader()
+            .yaml(config_yaml("http://127.0.0.1:9095"))
+            .env()
+            .set("uri.port", "9100")
+            .set("register.props.username", "operator")
+            .load_env(vars(&[
+                ("SHENYU_URI_PORT", "9000"),
+                ("SHENYU_URI_CONTEXT_PATH", "/env"),
+            ]))
+            .unwrap();
+        assert_eq!(config.uri.port, Some(9100));
+        assert_eq!(config.uri.context_path, "/env");
+        assert_eq!(config.register.props["username"], "operator");
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = ShenYuConfig::loader()
+            .file("/nonexistent/config.yml")
+            .load()
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid config: /nonexistent/config.yml: "));
+
+        let err = ShenYuConfig::loader()
+            .yaml(config_yaml("http://127.0.0.1:9095"))
+            .set("uri.port", "eighty")
+            .load()
+            .unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+    }
+
+    #[test]
+    fn test_toml_and_json() {
+        let toml = r#"
+[shenyu.register]
+register_type = "http"
+servers = "http://127.0.0.1:9095"
+props = { username = "admin", password = "123456" }
+
+[shenyu.uri]
+app_name = "app1"
+port = 8000
+context_path = "/xxx"
+rpc_type = "http"
+
+[shenyu.discovery]
+discovery_type = "zookeeper"
+server_lists = "127.0.0.1:2181"
+register_path = "/shenyu/discovery/http_example"
+props = { maxRetries = 4 }
+"#;
+        let config = ShenYuConfig::loader()
+            .text(toml, Format::Toml)
+            .load()
+            .unwrap();
+        assert_eq!(config.register.props["password"], "123456");
+        assert_eq!(config.uri.port, Some(8000));
+        assert_eq!(config.discovery.as_ref().unwrap().props["maxRetries"], "4");
+
+        let json = r#"{
+  "shenyu": {
+    "register": {"register_type": "http", "servers": "http://127.0.0.1:9095", "props": {"authType": "none"}},
+    "uri": {"app_name": "app1", "port": 8000, "context_path": "/xxx", "rpc_type": "http"},
+    "discovery": {"discovery_type": "zookeeper", "server_lists": "127.0.0.1:2181", "register_path": "/shenyu"}
+  }
+}"#;
+        let config = ShenYuConfig::loader()
+            .text(json, Format::Json)
+            .load()
+            .unwrap();
+        assert_eq!(config.register.servers, "http://127.0.0.1:9095");
+        assert_eq!(config.uri.context_path, "/xxx");
+    }
+
+    #[test]
+    fn test_spring_properties() {
+        let properties = r#"
+# migrated from the Java client
+spring.application.name=http-example
+shenyu.register.registerType=http
+shenyu.register.serverLists=http://localhost:9095
+shenyu.register.props.username=admin
+shenyu.register.props.password=123456
+shenyu.client.http.props.contextPath=/http
+shenyu.client.http.props.appName=http
+shenyu.client.http.props.port=8189
+shenyu.discovery.type=zookeeper
+shenyu.discovery.server-list=localhost:2181
+shenyu.discovery.registerPath=/shenyu/discovery/http\
+  _example
+shenyu.discovery.props.max-retries=4
+"#;
+        let config = ShenYuConfig::loader()
+            .text(properties, Format::Properties)
+            .load()
+            .unwrap();
+        assert_eq!(config.register.register_type, "http");
+        assert_eq!(config.register.servers, "http://localhost:9095");
+        assert_eq!(config.register.props["password"], "123456");
+        assert_eq!(config.uri.rpc_type, "http");
+        assert_eq!(config.uri.app_name, "http");
+        assert_eq!(config.uri.context_path, "/http");
+        assert_eq!(config.uri.port, Some(8189));
+        let discovery = config.discovery.unwrap();
+        assert_eq!(discovery.discovery_type, "zookeeper");
+        assert_eq!(discovery.server_lists, "localhost:2181");
+        assert_eq!(discovery.register_path, "/shenyu/discovery/http_example");
+        assert_eq!(discovery.props["maxRetries"], "4");
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("config.yml")), Format::Yaml);
+        assert_eq!(Format::from_path(Path::new("shenyu.toml")), Format::Toml);
+        assert_eq!(Format::from_path(Path::new("shenyu.json")), Format::Json);
+        assert_eq!(
+            Format::from_path(Path::new("application.properties")),
+            Format::Properties
+        );
+    }
+
+    #[test]
+    fn test_scalar() {
+        assert_eq!(scalar("8080"), Value::from(8080));
+        assert_eq!(scalar("true"), Value::from(true));
+        assert_eq!(scalar("007"), Value::from("007"));
+        assert_eq!(scalar("1e3"), Value::from("1e3"));
+        assert_eq!(scalar(""), Value::from(""));
+        assert_eq!(scalar("http://a:1"), Value::from("http://a:1"));
+    }
+
+    #[test]
+    fn test_key_case() {
+        assert_eq!(screaming_snake("tokenRefreshAhead"), "TOKEN_REFRESH_AHEAD");
+        assert_eq!(camel("TOKEN_REFRESH_AHEAD"), "tokenRefreshAhead");
+        assert_eq!(camel("PASSWORD"), "password");
+    }
+}
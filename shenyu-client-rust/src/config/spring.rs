@@ -0,0 +1,255 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The layout of the Java client's Spring Boot properties:
+//!
+//! ```yaml
+//! shenyu:
+//!   register:
+//!     registerType: http
+//!     serverLists: http://localhost:9095
+//!     props:
+//!       username: admin
+//!       password: 123456
+//!   client:
+//!     http:
+//!       props:
+//!         contextPath: /http
+//!         appName: http
+//!         port: 8189
+//!   discovery:
+//!     type: zookeeper
+//!     serverList: localhost:2181
+//!     registerPath: /shenyu/discovery/http
+//! ```
+//!
+//! Keys may use any of Spring's relaxed spellings, e.g. `server-lists`.
+
+use serde_yaml::{Mapping, Value};
+use tracing::warn;
+
+/// Whether the `shenyu` section of `root` follows the Java client's layout.
+pub(super) fn is_spring(root: &Value) -> bool {
+    let Some(shenyu) = root.get("shenyu").and_then(Value::as_mapping) else {
+        return false;
+    };
+    shenyu.contains_key("client")
+        || shenyu
+            .get("register")
+            .and_then(Value::as_mapping)
+            .is_some_and(|register| {
+                register
+                    .keys()
+                    .filter_map(Value::as_str)
+                    .any(|key| canonical(key) == "serverlists")
+            })
+}
+
+/// Rewrites the `shenyu` section of `root` from the Java client's layout to
+/// the one of [`super::ShenYuConfig`].
+pub(super) fn to_native(root: &mut Value) {
+    let Some(shenyu) = root.get_mut("shenyu").and_then(Value::as_mapping_mut) else {
+        return;
+    };
+    if let Some(Value::Mapping(register)) = shenyu.get_mut("register") {
+        *register = section(std::mem::take(register), |key| match key {
+            "registertype" => Some("register_type"),
+            "serverlists" => Some("servers"),
+            _ => None,
+        });
+    }
+    if let Some(Value::Mapping(discovery)) = shenyu.get_mut("discovery") {
+        *discovery = section(std::mem::take(discovery), |key| match key {
+            "type" => Some("discovery_type"),
+            "serverlist" | "serverlists" => Some("server_lists"),
+            _ => None,
+        });
+    }
+    if let Some(Value::Mapping(client)) = shenyu.remove("client") {
+        let uri = shenyu
+            .entry(Value::from("uri"))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Value::Mapping(uri) = uri {
+            client_to_uri(client, uri);
+        }
+    }
+}
+
+/// Fills `uri` from the `shenyu.client.<rpcType>.props` of `client`.
+fn client_to_uri(client: Mapping, uri: &mut Mapping) {
+    if client.len() > 1 {
+        warn!("Only the first of the shenyu.client sections is used");
+    }
+    let Some((Value::String(rpc_type), rpc)) = client.into_iter().next() else {
+        return;
+    };
+    uri.insert(Value::from("rpc_type"), Value::from(rpc_type));
+    let Some(props) = rpc.get("props").and_then(Value::as_mapping) else {
+        return;
+    };
+    for (key, value) in props {
+        let native = match key.as_str().map(canonical).as_deref() {
+            Some("appname") => "app_name",
+            Some("contextpath") => "context_path",
+            Some("host") => "host",
+            Some("port") => "port",
+            _ => continue,
+        };
+        uri.insert(Value::from(native), value.clone());
+    }
+}
+
+/// `section` with its keys renamed by `rename`, from their canonical form,
+/// or else to snake case; `props` keys are turned to camel case.
+fn section(section: Mapping, rename: fn(&str) -> Option<&'static str>) -> Mapping {
+    section
+        .into_iter()
+        .map(|(key, value)| {
+            let Some(name) = key.as_str() else {
+                return (key, value);
+            };
+            let canonical = canonical(name);
+            match (rename(&canonical), value) {
+                (Some(native), value) => (Value::from(native), value),
+                (None, Value::Mapping(props)) if canonical == "props" => {
+                    let props = props
+                        .into_iter()
+                        .map(|(key, value)| match key.as_str() {
+                            Some(key) => (Value::from(camel(key)), value),
+                            None => (key, value),
+                        })
+                        .collect();
+                    (Value::from("props"), Value::Mapping(props))
+                }
+                (None, value) => (Value::from(snake(name)), value),
+            }
+        })
+        .collect()
+}
+
+/// `server-lists`, `serverLists` and `server_lists` to `serverlists`.
+fn canonical(key: &str) -> String {
+    key.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// `registerPath` and `register-path` to `register_path`.
+fn snake(key: &str) -> String {
+    let mut name = String::new();
+    for c in key.chars() {
+        match c {
+            '-' => name.push('_'),
+            c if c.is_ascii_uppercase() => {
+                if !name.is_empty() {
+                    name.push('_');
+                }
+                name.push(c.to_ascii_lowercase());
+            }
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+/// `max-retries` and `max_retries` to `maxRetries`, `maxRetries` unchanged.
+fn camel(key: &str) -> String {
+    let mut name = String::new();
+    let mut upper = false;
+    for c in key.chars() {
+        match c {
+            '-' | '_' => upper = !name.is_empty(),
+            c if upper => {
+                name.push(c.to_ascii_uppercase());
+                upper = false;
+            }
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_native() {
+        let mut root: Value = serde_yaml::from_str(
+            r#"
+spring:
+  application:
+    name: http-example
+shenyu:
+  register:
+    register-type: http
+    server-lists: http://localhost:9095
+    props:
+      username: admin
+      token-refresh-ahead: 60
+  client:
+    http:
+      props:
+        contextPath: /http
+        appName: http
+        port: 8189
+        isFull: false
+  discovery:
+    type: zookeeper
+    serverList: localhost:2181
+    registerPath: /shenyu/discovery/http
+    props:
+      maxRetries: 4
+"#,
+        )
+        .unwrap();
+        assert!(is_spring(&root));
+        to_native(&mut root);
+
+        let expected: Value = serde_yaml::from_str(
+            r#"
+register_type: http
+servers: http://localhost:9095
+props:
+  username: admin
+  tokenRefreshAhead: 60
+"#,
+        )
+        .unwrap();
+        assert_eq!(root["shenyu"]["register"], expected);
+        let expected: Value = serde_yaml::from_str(
+            "rpc_type: http\ncontext_path: /http\napp_name: http\nport: 8189\n",
+        )
+        .unwrap();
+        assert_eq!(root["shenyu"]["uri"], expected);
+        let discovery = &root["shenyu"]["discovery"];
+        assert_eq!(discovery["discovery_type"], "zookeeper");
+        assert_eq!(discovery["server_lists"], "localhost:2181");
+        assert_eq!(discovery["register_path"], "/shenyu/discovery/http");
+        assert_eq!(discovery["props"]["maxRetries"], 4);
+        assert!(root["shenyu"].get("client").is_none());
+    }
+
+    #[test]
+    fn test_native_is_not_spring() {
+        let root: Value =
+            serde_yaml::from_str(&crate::test_support::config_yaml("http://127.0.0.1:9095"))
+                .unwrap();
+        assert!(!is_spring(&root));
+    }
+}
@@ -0,0 +1,219 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use super::{DiscoveryConfig, RegisterConfig, ShenYuConfig, UriConfig};
+use crate::auth;
+use crate::error::{Error, Result};
+use reqwest::Url;
+
+/// The register centers this crate ships a registrar for.
+const REGISTER_TYPES: &[&str] = &["http", "consul", "etcd", "nacos", "zookeeper"];
+
+/// The `rpcType`s known to the ShenYu admin.
+const RPC_TYPES: &[&str] = &[
+    "http",
+    "springCloud",
+    "dubbo",
+    "sofa",
+    "tars",
+    "motan",
+    "grpc",
+    "brpc",
+    "websocket",
+];
+
+impl ShenYuConfig {
+    /// Checks the config, reporting every problem found rather than the
+    /// first one: invalid `register.servers`, missing credentials, an empty
+    /// `uri.app_name`, an unknown `uri.rpc_type`, an incomplete `discovery`.
+    ///
+    /// [`ShenYuConfig::loader`] validates what it loads.
+    pub fn validate(&self) -> Result<()> {
+        self.check(true)
+    }
+
+    /// Like [`ShenYuConfig::validate`], checking the credentials of the
+    /// `authType` only when `credentials`, e.g. not when the client is given
+    /// another [`crate::auth::AuthStrategy`].
+    pub(crate) fn check(&self, credentials: bool) -> Result<()> {
+        let mut problems = Vec::new();
+        register(&self.register, credentials, &mut problems);
+        uri(&self.uri, &mut problems);
+        if let Some(discovery) = &self.discovery {
+            self::discovery(discovery, &mut problems);
+        }
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Config(problems.join("; "))),
+        }
+    }
+}
+
+fn register(register: &RegisterConfig, credentials: bool, problems: &mut Vec<String>) {
+    let register_type = register.register_type.as_str();
+    if !REGISTER_TYPES.contains(&register_type) {
+        problems.push(format!(
+            "register.register_type: unsupported {register_type}, expected one of {}",
+            REGISTER_TYPES.join(", ")
+        ));
+    }
+    if register.servers.trim().is_empty() {
+        problems.push("register.servers is empty".to_string());
+    }
+    for server in register.servers.split(',').map(str::trim) {
+        if server.is_empty() {
+            continue;
+        }
+        let valid = match register_type {
+            "http" => Url::parse(server).is_ok_and(|url| is_http(&url)),
+            "zookeeper" => server
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok()),
+            // the scheme is optional, plain `http` is assumed
+            _ if server.contains("://") => Url::parse(server).is_ok_and(|url| is_http(&url)),
+            _ => Url::parse(&format!("http://{server}")).is_ok(),
+        };
+        if !valid {
+            let expected = match register_type {
+                "http" => "an http(s) URL",
+                "zookeeper" => "host:port",
+                _ => "an http(s) URL or host:port",
+            };
+            problems.push(format!("register.servers: {server} is not {expected}"));
+        }
+    }
+
+    if !credentials {
+        return;
+    }
+    let required: &[&str] = match (register_type, register.props.get("authType")) {
+        ("http", None) => &["username", "password"],
+        ("http", Some(auth_type)) => match auth_type.as_str() {
+            "token" => &["username", "password"],
+            "bearer" => &["token"],
+            "hmac" => &["appKey", "appSecret"],
+            "none" => &[],
+            other => {
+                problems.push(format!(
+                    "register.props.authType: unsupported strategy {other}"
+                ));
+                &[]
+            }
+        },
+        _ => &[],
+    };
+    for key in required {
+        match auth::credential(&register.props, key, |name| std::env::var(name).ok()) {
+            Ok(value) if value.is_empty() => {
+                problems.push(format!("register.props.{key} is empty"))
+            }
+            Ok(_) => {}
+            Err(Error::Config(problem)) => problems.push(problem),
+            Err(e) => problems.push(e.to_string()),
+        }
+    }
+}
+
+fn is_http(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https") && url.has_host()
+}
+
+fn uri(uri: &UriConfig, problems: &mut Vec<String>) {
+    if uri.app_name.trim().is_empty() {
+        problems.push("uri.app_name is empty".to_string());
+    }
+    if !RPC_TYPES.contains(&uri.rpc_type.as_str()) {
+        problems.push(format!(
+            "uri.rpc_type: unknown {}, expected one of {}",
+            uri.rpc_type,
+            RPC_TYPES.join(", ")
+        ));
+    }
+}
+
+fn discovery(discovery: &DiscoveryConfig, problems: &mut Vec<String>) {
+    for (key, value) in [
+        ("discovery_type", &discovery.discovery_type),
+        ("server_lists", &discovery.server_lists),
+        ("register_path", &discovery.register_path),
+    ] {
+        if value.trim().is_empty() {
+            problems.push(format!("discovery.{key} is empty"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ShenYuConfig;
+    use crate::test_support::config_yaml;
+
+    #[test]
+    fn test_validate() {
+        let config = ShenYuConfig::loader()
+            .yaml(config_yaml("http://127.0.0.1:9095"))
+            .load()
+            .unwrap();
+        assert!(config.validate().is_ok());
+
+        let err = ShenYuConfig::loader()
+            .yaml("shenyu:\n  register:\n    servers: 127.0.0.1:9095,ftp://admin\n  uri:\n    rpc_type: rest\n  discovery:\n    discovery_type: zookeeper\n    server_lists: ''\n    register_path: /shenyu\n")
+            .load()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.servers: 127.0.0.1:9095 is not an http(s) URL; \
+             register.servers: ftp://admin is not an http(s) URL; \
+             register.props.username is required to log in; \
+             register.props.password is required to log in; \
+             uri.app_name is empty; \
+             uri.rpc_type: unknown rest, expected one of http, springCloud, dubbo, sofa, tars, motan, grpc, brpc, websocket; \
+             discovery.server_lists is empty"
+        );
+    }
+
+    #[test]
+    fn test_validate_register_types() {
+        let config = |register: &str| {
+            ShenYuConfig::loader()
+                .yaml(format!(
+                    "shenyu:\n  register:\n{register}\n  uri:\n    app_name: app1\n"
+                ))
+                .load()
+        };
+        assert!(config("    register_type: zookeeper\n    servers: zk-0:2181,zk-1:2181").is_ok());
+        assert!(config("    register_type: consul\n    servers: consul:8500").is_ok());
+        assert!(config("    servers: http://admin:9095\n    props: {authType: none}").is_ok());
+
+        let err = config("    register_type: zookeeper\n    servers: zk-0").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.servers: zk-0 is not host:port"
+        );
+        let err = config("    register_type: kafka\n    servers: kafka:9092").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid config: register.register_type: unsupported kafka"));
+        let err =
+            config("    servers: http://admin:9095\n    props: {authType: hmac, appKey: app}")
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.props.appSecret is required to log in"
+        );
+    }
+}
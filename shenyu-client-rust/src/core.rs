@@ -15,379 +15,862 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::config::ShenYuConfig;
-use crate::error::ShenYuError;
-use crate::model::{EventType, UriInfo};
+use crate::auth::TokenLogin;
+use crate::config::{HeartbeatConfig, ShenYuConfig, UriConfig};
+use crate::error::{Error, Result};
+use crate::heartbeat::{self, HeartbeatHandle};
+use crate::model::{DiscoveryConfigRegister, EventType, MetaDataRegister, UriInfo, UriRegister};
+use crate::registrar::Registrar;
+use crate::report::{Attempt, ItemKind, ItemReport, RegistrationReport};
+use crate::{network, rt};
 use dashmap::DashMap;
-use serde_json::Value;
-use std::io::{Error, ErrorKind};
-use std::net::IpAddr;
-use tracing::{error, info, warn};
-use ureq::OrAnyStatus;
-
-pub const REGISTER_META_DATA_SUFFIX: &str = "/shenyu-client/register-metadata";
-pub const REGISTER_URI_SUFFIX: &str = "/shenyu-client/register-uri";
-pub const REGISTER_DISCOVERY_CONFIG_SUFFIX: &str = "/shenyu-client/register-discoveryConfig";
-pub const REGISTER_OFFLINE_SUFFIX: &str = "/shenyu-client/offline";
-pub const PLATFORM_LOGIN_SUFFIX: &str = "/platform/login";
+use std::future::Future;
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::{error, info};
+
+mod builder;
+
+pub use crate::registrar::http::{
+    PLATFORM_LOGIN_SUFFIX, REGISTER_DISCOVERY_CONFIG_SUFFIX, REGISTER_META_DATA_SUFFIX,
+    REGISTER_OFFLINE_SUFFIX, REGISTER_URI_SUFFIX,
+};
+pub use builder::ShenyuClientBuilder;
 
 #[derive(Debug)]
 #[warn(dead_code)]
 pub struct ShenyuClient {
-    pub(super) headers: DashMap<String, String>,
+    pub(super) headers: Arc<DashMap<String, String>>,
+    http_client: reqwest::Client,
+    registrar: Arc<dyn Registrar>,
+    heartbeat: Mutex<Option<HeartbeatHandle>>,
     app_name: String,
     env: ShenYuConfig,
-    host: Option<String>,
+    host: String,
     port: u16,
-    gateway_base_urls: Vec<String>,
-    register_meta_data_path_list: Vec<String>,
-    register_uri_list: Vec<String>,
-    register_token_servers: Vec<String>,
-    register_discover_config_servers: Vec<String>,
-    register_offline_servers: Vec<String>,
     uri_infos: Vec<UriInfo>,
 }
 
 impl ShenyuClient {
-    pub fn register(&self) -> Result<(), Error> {
-        if let Ok(token) = self.get_register_token() {
-            self.headers
-                .insert("X-Access-Token".to_string(), token.to_string());
-        } else {
-            return Err(Error::new(ErrorKind::Other, "Can't get register token"));
+    /// Blocking version of [`ShenyuClient::register_async`].
+    pub fn register(&self) -> Result<RegistrationReport> {
+        rt::block_on(self.register_async())?
+    }
+
+    /// Logs in to the register center, then registers all metadata, the URI
+    /// and the discovery config without blocking the calling runtime.
+    ///
+    /// Every call is retried as configured by the `retry` section. An item
+    /// that can't be registered doesn't stop the others: they are all tried,
+    /// and [`Error::Incomplete`] carries the report of what failed. Starts
+    /// the heartbeat as well when `register.heartbeat` is configured and
+    /// everything was registered.
+    pub async fn register_async(&self) -> Result<RegistrationReport> {
+        let res = self.try_register().await;
+        match &res {
+            Ok(_) => {}
+            Err(e @ Error::Incomplete(report)) => {
+                error!("[ERROR], register failed: {}\n{}", e, report)
+            }
+            Err(e) => error!("[ERROR], register failed: {}", e),
+        }
+        res
+    }
+
+    async fn try_register(&self) -> Result<RegistrationReport> {
+        self.env
+            .retry
+            .run("login", || self.registrar.login())
+            .await?;
+        let mut report = RegistrationReport::default();
+        let mut results = vec![
+            self.register_all_metadata_async(true).await,
+            self.register_uri_async().await,
+        ];
+        if self.env.discovery.is_some() {
+            results.push(self.register_discovery_config_async().await);
+        }
+        for result in results {
+            match result {
+                Ok(items) => report.extend(items),
+                Err(Error::Incomplete(items)) => report.extend(*items),
+                Err(e) => return Err(e),
+            }
+        }
+        let report = report.into_result()?;
+        if let Some(config) = &self.env.register.heartbeat {
+            self.start_heartbeat(config.clone())?;
+        }
+        Ok(report)
+    }
+
+    /// Registers one item through `op`, retried as configured, and reports
+    /// every request it sent.
+    ///
+    /// `op` returns the outcome of one try along with the requests it sent.
+    async fn register_item<F, Fut>(&self, kind: ItemKind, name: String, mut op: F) -> ItemReport
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = (Result<()>, Vec<Attempt>)>,
+    {
+        let attempts = Mutex::new(Vec::new());
+        let res = self
+            .env
+            .retry
+            .run(&format!("register {kind}"), || {
+                let tried = op();
+                let attempts = &attempts;
+                async move {
+                    let (res, tried) = tried.await;
+                    attempts
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .extend(tried);
+                    res
+                }
+            })
+            .await;
+        ItemReport {
+            kind,
+            name,
+            attempts: attempts
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner),
+            error: res.err(),
         }
-        self.register_all_metadata(true)
-            .expect("Failed to register metadata");
-        self.register_uri().expect("Failed to register URI");
-        self.register_discovery_config()
-            .expect("Failed to register discovery config");
-        Ok(())
     }
 
+    /// Starts re-registering the URI (and the metadata, if enabled) in the
+    /// background, replacing any heartbeat already running.
+    ///
+    /// The heartbeat stops with the returned handle or on
+    /// [`ShenyuClient::offline_register_async`].
+    pub fn start_heartbeat(&self, config: HeartbeatConfig) -> Result<HeartbeatHandle> {
+        let metadata = self
+            .uri_infos
+            .iter()
+            .map(|x| self.metadata_register(x, true))
+            .collect();
+        let handle = heartbeat::spawn(
+            self.registrar.clone(),
+            config,
+            self.uri_register(EventType::REGISTER),
+            metadata,
+        )?;
+        let previous = self
+            .heartbeat
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(handle.clone());
+        if let Some(previous) = previous {
+            previous.stop();
+        }
+        Ok(handle)
+    }
+
+    /// Stops the heartbeat, if one is running.
+    pub fn stop_heartbeat(&self) {
+        let handle = self
+            .heartbeat
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(handle) = handle {
+            handle.stop();
+        }
+    }
+
+    /// Builds a client registering `uri_infos` for `app_name`, advertised on
+    /// `port` unless `uri.port` is set.
+    #[deprecated(since = "0.1.2", note = "use `ShenyuClient::builder()` instead")]
     pub fn new(
-        config: ShenYuConfig,
+        mut config: ShenYuConfig,
         app_name: &str,
         uri_infos: &[UriInfo],
         port: u16,
-    ) -> Result<Self, String> {
-        let headers = DashMap::new();
-        headers.insert(
-            "Content-Type".to_string(),
-            "application/json;charset=UTF-8".to_string(),
-        );
-        let mut client = ShenyuClient {
-            headers,
-            app_name: app_name.to_string(),
-            env: config,
-            host: None,
-            port,
-            gateway_base_urls: vec![],
-            register_meta_data_path_list: vec![],
-            register_uri_list: vec![],
-            register_token_servers: vec![],
-            register_discover_config_servers: vec![],
-            register_offline_servers: vec![],
-            uri_infos: uri_infos.to_owned(),
-        };
-        client.set_up_gateway_service_url()?;
-        Ok(client)
+    ) -> Result<Self> {
+        config.uri.port.get_or_insert(port);
+        Self::builder()
+            .config(config)
+            .app_name(app_name)
+            .uri_infos(uri_infos)
+            .build_unchecked()
     }
+
+    /// Replaces the registrar selected from `register.register_type`, e.g. to
+    /// register to a register center this crate doesn't ship.
+    pub fn with_registrar<R: Registrar + 'static>(mut self, registrar: R) -> Self {
+        self.registrar = Arc::new(registrar);
+        self
+    }
+}
+
+/// The host and port to advertise to the register center: the ones given
+/// to the builder, else from `uri`, else the address picked as told by
+/// `uri.network`.
+fn advertised_address(
+    uri: &UriConfig,
+    host: Option<String>,
+    port: Option<u16>,
+) -> Result<(String, u16)> {
+    let port = port.or(uri.port).ok_or_else(|| {
+        Error::Config("uri.port is not set, nor the port of the builder".to_string())
+    })?;
+    let host = match host
+        .or_else(|| uri.host.clone())
+        .filter(|host| !host.is_empty())
+    {
+        // an IPv6 address may be written as in a URL
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+        None => network::local_host(&uri.network)?.to_string(),
+    };
+    Ok((host, port))
 }
 
 impl ShenyuClient {
-    fn set_up_gateway_service_url(&mut self) -> Result<(), String> {
-        self.gateway_base_urls = self
-            .env
-            .register
-            .servers
-            .split(',')
-            .map(|s| s.to_string())
-            .collect();
+    /// Blocking version of [`ShenyuClient::get_register_token_async`].
+    pub fn get_register_token(&self) -> Result<String> {
+        rt::block_on(self.get_register_token_async())?
+    }
 
-        self.register_meta_data_path_list = self
-            .gateway_base_urls
-            .iter()
-            .map(|url| format!("{}{}", url, REGISTER_META_DATA_SUFFIX))
-            .collect();
-        self.register_uri_list = self
-            .gateway_base_urls
-            .iter()
-            .map(|url| format!("{}{}", url, REGISTER_URI_SUFFIX))
-            .collect();
-        self.register_token_servers = self
-            .gateway_base_urls
-            .iter()
-            .map(|url| format!("{}{}", url, PLATFORM_LOGIN_SUFFIX))
-            .collect();
-        self.register_discover_config_servers = self
-            .gateway_base_urls
-            .iter()
-            .map(|url| format!("{}{}", url, REGISTER_DISCOVERY_CONFIG_SUFFIX))
-            .collect();
-        self.register_offline_servers = self
-            .gateway_base_urls
-            .iter()
-            .map(|url| format!("{}{}", url, REGISTER_OFFLINE_SUFFIX))
-            .collect();
+    /// Logs in to the admin and returns the `X-Access-Token` to register with.
+    pub async fn get_register_token_async(&self) -> Result<String> {
+        let login = TokenLogin::new(
+            &self.env.register,
+            self.http_client.clone(),
+            self.headers.clone(),
+        );
+        self.env
+            .retry
+            .run("get register token", || login.get_register_token())
+            .await
+    }
 
-        let mut host = None;
-        #[cfg(not(target_os = "macos"))]
-        {
-            host = match local_ip_address::local_ip() {
-                Ok(IpAddr::V4(ipv4)) => Some(IpAddr::V4(ipv4)),
-                Ok(IpAddr::V6(ipv6)) => Some(IpAddr::from(ipv6.to_ipv4().unwrap())),
-                _ => None,
-            };
+    fn uri_register(&self, event_type: EventType) -> UriRegister {
+        UriRegister {
+            app_name: self.app_name.clone(),
+            context_path: self.env.uri.context_path.clone(),
+            protocol: self.env.uri.rpc_type.clone(),
+            rpc_type: self.env.uri.rpc_type.clone(),
+            host: self.host.clone(),
+            port: self.port,
+            event_type: event_type.to_string(),
         }
-        #[cfg(target_os = "macos")]
-        {
-            use local_ip_address::macos;
-            for (_, ipaddr) in macos::list_afinet_netifas().unwrap() {
-                if IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 1)).eq(&ipaddr) {
-                    continue;
-                }
-                host = match ipaddr {
-                    IpAddr::V4(ipv4) => Some(IpAddr::from(ipv4)),
-                    _ => continue,
-                };
-            }
+    }
+
+    /// Blocking version of [`ShenyuClient::register_uri_async`].
+    pub fn register_uri(&self) -> Result<RegistrationReport> {
+        rt::block_on(self.register_uri_async())?
+    }
+
+    pub async fn register_uri_async(&self) -> Result<RegistrationReport> {
+        let uri = self.uri_register(EventType::REGISTER);
+        let name = network::host_port(&uri.host, uri.port);
+
+        let item = self
+            .register_item(ItemKind::Uri, name, || async {
+                let mut attempts = Vec::new();
+                let res = self.registrar.register_uri(&uri, &mut attempts).await;
+                (res, attempts)
+            })
+            .await;
+        match &item.error {
+            None => info!("[SUCCESS], register uri success, register data: {:#?}", uri),
+            Some(e) => error!(
+                "[ERROR], register uri failed, app_name: {}, host: {}, port: {}: {}",
+                self.app_name, uri.host, self.port, e
+            ),
         }
-        if let Some(host) = host {
-            self.host = Some(host.to_string());
-            Ok(())
-        } else {
-            Err("Failed to determine local IP address".to_string())
+        RegistrationReport { items: vec![item] }.into_result()
+    }
+
+    /// Blocking version of [`ShenyuClient::register_all_metadata_async`].
+    pub fn register_all_metadata(&self, enabled: bool) -> Result<RegistrationReport> {
+        rt::block_on(self.register_all_metadata_async(enabled))?
+    }
+
+    /// Registers the metadata of every route, going on past the ones that
+    /// fail; see [`Error::Incomplete`].
+    pub async fn register_all_metadata_async(&self, enabled: bool) -> Result<RegistrationReport> {
+        let mut report = RegistrationReport::default();
+        for x in self.uri_infos.iter() {
+            let item = self.register_metadata(x, enabled).await;
+            report.push(item);
         }
+        report.into_result()
     }
 
-    fn request(&self, url: &str, json_data: &Value) -> Result<bool, Error> {
-        let mut builder = ureq::post(url);
-        // 遍历header， 添加到builder中
-        for r in &self.headers {
-            builder = builder.set(r.key(), r.value());
+    async fn register_metadata(&self, uri_info: &UriInfo, enabled: bool) -> ItemReport {
+        let metadata = self.metadata_register(uri_info, enabled);
+
+        let item = self
+            .register_item(ItemKind::Metadata, metadata.path.clone(), || async {
+                let mut attempts = Vec::new();
+                let res = self
+                    .registrar
+                    .register_metadata(&metadata, &mut attempts)
+                    .await;
+                (res, attempts)
+            })
+            .await;
+        match &item.error {
+            None => info!(
+                "[SUCCESS], register metadata success, register data: {:#?}",
+                metadata
+            ),
+            Some(e) => error!(
+                "[ERROR], register metadata failed, app_name: {}, path: {}, contextPath: {}: {}",
+                self.app_name, metadata.path, metadata.context_path, e
+            ),
         }
-        let res = builder.send_json(json_data).unwrap();
-        let status_code = res.status();
-        let msg = res.into_string()?;
-
-        if msg == "success" {
-            Ok(true)
-        } else {
-            warn!(
-                "Request ({}) failed, status code: {}, msg: {}",
-                url, status_code, msg
-            );
-            Ok(false)
+        item
+    }
+
+    /// The metadata of `uri_info`, enabled when both `enabled` and the
+    /// route are.
+    fn metadata_register(&self, uri_info: &UriInfo, enabled: bool) -> MetaDataRegister {
+        let context_path = &self.env.uri.context_path;
+        MetaDataRegister {
+            app_name: self.app_name.clone(),
+            context_path: context_path.clone(),
+            path: context_path.clone() + uri_info.path.as_str(),
+            path_desc: uri_info.description.clone(),
+            rpc_type: self.env.uri.rpc_type.clone(),
+            rule_name: context_path.clone() + uri_info.rule_name.as_str(),
+            service_name: uri_info
+                .service_name
+                .clone()
+                .unwrap_or_else(|| self.app_name.clone()),
+            method_name: uri_info.method_name.clone(),
+            parameter_types: uri_info.parameter_types.clone(),
+            rpc_ext: uri_info.rpc_ext.clone(),
+            host: self.host.clone(),
+            port: self.port,
+            enabled: enabled && uri_info.enabled,
+            register_meta_data: false,
+            plugin_names: uri_info.plugin_names.clone(),
         }
     }
 
-    pub(crate) fn get_register_token(&self) -> Result<String, Error> {
-        let hashmap = &self.env.register.props;
-        let params = [
-            ("userName", hashmap.get("username").unwrap().as_str()),
-            ("password", hashmap.get("password").unwrap().as_str()),
-        ];
+    /// Blocking version of [`ShenyuClient::register_discovery_config_async`].
+    pub fn register_discovery_config(&self) -> Result<RegistrationReport> {
+        rt::block_on(self.register_discovery_config_async())?
+    }
 
-        let result = Err(ShenYuError::new(500, "Can't get register token".to_string()).into());
-        for url in &self.register_token_servers {
-            let res_data: Value = ureq::get(url)
-                .query_pairs(params)
-                .call()
-                .or_any_status()
-                .map_err(|e| Error::new(ErrorKind::Other, format!("{e}")))?
-                .into_json()?;
-            match res_data
-                .get("data")
-                .and_then(|data| data.get("token"))
-                .and_then(|token| token.as_str())
-            {
-                Some(token) => return Ok(token.to_string()),
-                None => continue,
-            }
+    /// Registers the `discovery` section of the config, which must be set.
+    pub async fn register_discovery_config_async(&self) -> Result<RegistrationReport> {
+        let discovery = self
+            .env
+            .discovery
+            .as_ref()
+            .ok_or_else(|| Error::Config("discovery is not set".to_string()))?;
+        let config = DiscoveryConfigRegister {
+            name: "default".to_string() + &discovery.discovery_type,
+            selector_name: self.env.uri.context_path.clone(),
+            handler: "{}".to_string(),
+            listener_node: discovery.register_path.clone(),
+            server_list: discovery.server_lists.clone(),
+            props: discovery.props.clone(),
+            discovery_type: discovery.discovery_type.clone(),
+            plugin_name: discovery.plugin_name.clone(),
+        };
+
+        let item = self
+            .register_item(ItemKind::DiscoveryConfig, config.name.clone(), || async {
+                let mut attempts = Vec::new();
+                let res = self
+                    .registrar
+                    .register_discovery_config(&config, &mut attempts)
+                    .await;
+                (res, attempts)
+            })
+            .await;
+        match &item.error {
+            None => info!(
+                "[SUCCESS], register discover config success, register data: {:#?}",
+                config
+            ),
+            Some(e) => error!(
+                "[ERROR], register discover config failed, discovery_type: {}, host: {}, port: {}: {}",
+                discovery.discovery_type, self.host, self.port, e
+            ),
         }
-        result
+        RegistrationReport { items: vec![item] }.into_result()
     }
 
-    pub fn register_uri(&self) -> Result<bool, Error> {
-        let app_name = &self.app_name.clone();
-        let rpc_type = &self.env.uri.rpc_type.clone();
-        let context_path = &self.env.uri.context_path.clone();
-
-        let port = &self.port;
-        let host = &self.host;
+    /// Blocking version of [`ShenyuClient::offline_register_async`].
+    pub fn offline_register(&self) -> Result<()> {
+        rt::block_on(self.offline_register_async())?
+    }
 
-        let json_data = serde_json::json!({
-            "appName": app_name,
-            "contextPath": context_path,
-            "protocol": rpc_type,
-            "rpcType": rpc_type,
-            "host": host.clone().unwrap(),
-            "port": port,
-            "eventType": EventType::REGISTER.to_string(),
-        });
+    /// Stops the heartbeat and removes this instance from the register center.
+    pub async fn offline_register_async(&self) -> Result<()> {
+        self.stop_heartbeat();
+        let uri = self.uri_register(EventType::REGISTER);
 
-        for url in &self.register_uri_list {
-            if self.request(url, &json_data)? {
-                info!(
-                    "[SUCCESS], register uri success, register data: {:#?}",
-                    json_data
+        match self.registrar.offline(&uri).await {
+            Ok(()) => {
+                info!("[SUCCESS], offline success, register data: {:#?}", uri);
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    "[ERROR], offline failed, app_name: {}, host: {}, port: {}: {}",
+                    self.app_name, uri.host, self.port, e
                 );
-                return Ok(true);
+                Err(e)
             }
         }
+    }
+}
 
-        error!(
-            "[ERROR], register uri failed, app_name: {}, host: {}, port: {}",
-            app_name,
-            host.clone().unwrap(),
-            port
-        );
-        Ok(false)
+impl Drop for ShenyuClient {
+    fn drop(&mut self) {
+        self.stop_heartbeat();
     }
+}
 
-    pub fn register_all_metadata(&self, enabled: bool) -> Result<bool, Error> {
-        for x in self.uri_infos.iter() {
-            match self.register_metadata(
-                false,
-                Some(&x.path),
-                Some(&x.method_name),
-                Some(&x.rule_name),
-                enabled,
-            ) {
-                Ok(true) => continue,
-                Ok(false) => return Ok(false),
-                Err(e) => return Err(e),
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, FakeServer};
+
+    fn client(admin: &FakeServer) -> ShenyuClient {
+        let uri_infos = vec![UriInfo::new("/health", "get")];
+        ShenyuClient::builder()
+            .config(config(&admin.base_url))
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap()
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingRegistrar {
+        calls: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Registrar for RecordingRegistrar {
+        async fn register_metadata(
+            &self,
+            metadata: &MetaDataRegister,
+            _attempts: &mut Vec<Attempt>,
+        ) -> Result<()> {
+            self.calls.lock().unwrap().push(metadata.path.clone());
+            Ok(())
         }
-        Ok(true)
-    }
-
-    fn register_metadata(
-        &self,
-        register_all: bool,
-        path: Option<&str>,
-        method: Option<&str>,
-        rule_name: Option<&str>,
-        enabled: bool,
-    ) -> Result<bool, Error> {
-        let context_path = &self.env.uri.context_path.clone();
-        let app_name = &self.app_name.clone();
-        let rpc_type = &self.env.uri.rpc_type.clone();
-        let path = if register_all {
-            format!("{}**", context_path)
-        } else {
-            path.unwrap_or("").to_string()
-        };
 
-        let rule_name = rule_name.unwrap_or(&path).to_string();
-        let json_data = serde_json::json!({
-            "appName": app_name.clone(),
-            "contextPath": context_path.clone(),
-            "path": context_path.clone() + path.as_str(),
-            "pathDesc": "",
-            "rpcType": rpc_type,
-            "ruleName": context_path.clone() + rule_name.as_str(),
-            "serviceName": app_name.clone(),
-            "methodName": method.unwrap_or("").to_string(),
-            "parameterTypes": "",
-            "rpcExt": "",
-            "host": self.host.clone().unwrap(),
-            "port": self.port,
-            "enabled": enabled,
-            "registerMetaData": "",
-            "pluginNames": []
-        });
+        async fn register_uri(
+            &self,
+            uri: &UriRegister,
+            _attempts: &mut Vec<Attempt>,
+        ) -> Result<()> {
+            self.calls.lock().unwrap().push(uri.app_name.clone());
+            Ok(())
+        }
 
-        for url in &self.register_meta_data_path_list {
-            if self.request(url, &json_data)? {
-                info!(
-                    "[SUCCESS], register metadata success, register data: {:#?}",
-                    json_data
-                );
-                return Ok(true);
-            }
+        async fn register_discovery_config(
+            &self,
+            config: &DiscoveryConfigRegister,
+            _attempts: &mut Vec<Attempt>,
+        ) -> Result<()> {
+            self.calls.lock().unwrap().push(config.name.clone());
+            Err(Error::Rejected {
+                status: 200,
+                body: "discovery disabled".to_string(),
+            })
         }
 
-        error!(
-            "[ERROR], register metadata failed, app_name: {}, path: {}, contextPath: {}",
-            app_name, path, context_path
+        async fn offline(&self, _uri: &UriRegister) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_unsupported_register_type() {
+        let mut config = config("http://127.0.0.1:9095");
+        config.register.register_type = "eureka".to_string();
+        let err = ShenyuClient::new(config, "app1", &[], 9527).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedRegisterType(_)));
+        assert_eq!(err.to_string(), "Unsupported register type: eureka");
+    }
+
+    #[test]
+    fn test_advertised_address() {
+        let mut uri = UriConfig {
+            host: Some("[fd00::7]".to_string()),
+            port: Some(8080),
+            ..Default::default()
+        };
+        assert_eq!(
+            advertised_address(&uri, None, None).unwrap(),
+            ("fd00::7".to_string(), 8080)
+        );
+        // the builder's host and port win over the config
+        assert_eq!(
+            advertised_address(&uri, Some("10.0.0.8".to_string()), Some(8081)).unwrap(),
+            ("10.0.0.8".to_string(), 8081)
         );
-        Ok(false)
-    }
-
-    pub fn register_discovery_config(&self) -> Result<bool, Error> {
-        let discovery_type = &self.env.discovery.discovery_type.clone();
-        let register_path = &self.env.discovery.register_path.clone();
-        let server_lists = &self.env.discovery.server_lists.clone();
-        let props = &self.env.discovery.props.clone();
-        let plugin_name = &self.env.discovery.plugin_name.clone();
-        let context_path = &self.env.uri.context_path.clone();
-
-        let port = &self.port;
-        let host = &self.host;
-
-        let json_data = serde_json::json!({
-            "name": "default".to_string() + discovery_type,
-            "selectorName": context_path,
-            "handler": "{}",
-            "listenerNode":register_path,
-            "serverList": server_lists,
-            "props": props,
-            "discoveryType": discovery_type.clone(),
-            "pluginName": plugin_name,
-        });
 
-        for url in &self.register_discover_config_servers {
-            if self.request(url, &json_data)? {
-                info!(
-                    "[SUCCESS], register discover config success, register data: {:#?}",
-                    json_data
-                );
-                return Ok(true);
-            }
-        }
+        uri.port = None;
+        let err = advertised_address(&uri, None, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: uri.port is not set, nor the port of the builder"
+        );
+    }
 
-        error!(
-            "[ERROR], register discover config failed, discovery_type: {}, host: {}, port: {}",
-            discovery_type,
-            host.clone().unwrap(),
-            port
+    #[tokio::test]
+    async fn test_uri_host_and_port() {
+        let admin = FakeServer::admin();
+        let mut config = config(&admin.base_url);
+        config.uri.host = Some("10.0.0.7".to_string());
+        config.uri.port = Some(8080);
+        let client = ShenyuClient::builder()
+            .config(config)
+            .app_name("app1")
+            .build()
+            .unwrap();
+        client.register_uri_async().await.unwrap();
+
+        let uri = &admin.requests_to(REGISTER_URI_SUFFIX)[0];
+        assert_eq!(uri.json()["host"], "10.0.0.7");
+        assert_eq!(uri.json()["port"], 8080);
+    }
+
+    #[tokio::test]
+    async fn test_custom_registrar() {
+        let admin = FakeServer::admin();
+        let client = client(&admin).with_registrar(RecordingRegistrar::default());
+        client.register_all_metadata_async(true).await.unwrap();
+        client.register_uri_async().await.unwrap();
+        assert!(admin.requests().is_empty());
+        let err = client.register_discovery_config_async().await.unwrap_err();
+        let Error::Incomplete(report) = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert!(matches!(
+            report.items[0].error,
+            Some(Error::Rejected { status: 200, .. })
+        ));
+        assert!(client.register_async().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_register_async() {
+        let admin = FakeServer::admin();
+        let client = client(&admin);
+        client.register_async().await.unwrap();
+
+        let metadata = admin.requests_to(REGISTER_META_DATA_SUFFIX);
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].json()["path"], "/xxx/health");
+        assert_eq!(
+            metadata[0].headers.get("X-Access-Token").unwrap(),
+            "fake-token"
         );
-        Ok(false)
+        assert_eq!(admin.requests_to(REGISTER_URI_SUFFIX).len(), 1);
+        assert_eq!(admin.requests_to(REGISTER_DISCOVERY_CONFIG_SUFFIX).len(), 1);
+
+        client.offline_register_async().await.unwrap();
+        assert_eq!(admin.requests_to(REGISTER_OFFLINE_SUFFIX).len(), 1);
     }
 
-    pub fn offline_register(&self) {
-        let app_name = &self.app_name.clone();
-        let rpc_type = &self.env.uri.rpc_type.clone();
-        let context_path = &self.env.uri.context_path.clone();
+    #[tokio::test]
+    async fn test_register_metadata_fields() {
+        let admin = FakeServer::admin();
+        let uri_infos = vec![
+            UriInfo::new("/users", "post")
+                .service_name("UserController")
+                .description("Creates a user")
+                .parameter_types("java.lang.String,int")
+                .rpc_ext(r#"{"timeout":3000}"#)
+                .plugin_names(["rateLimiter", "sign"]),
+            UriInfo::new("/health", "get").enabled(false),
+        ];
+        let client = ShenyuClient::builder()
+            .config(config(&admin.base_url))
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap();
+        client.register_all_metadata_async(true).await.unwrap();
+
+        let metadata = admin.requests_to(REGISTER_META_DATA_SUFFIX);
+        let users = metadata[0].json();
+        assert_eq!(users["serviceName"], "UserController");
+        assert_eq!(users["pathDesc"], "Creates a user");
+        assert_eq!(users["parameterTypes"], "java.lang.String,int");
+        assert_eq!(users["rpcExt"], r#"{"timeout":3000}"#);
+        assert_eq!(
+            users["pluginNames"],
+            serde_json::json!(["rateLimiter", "sign"])
+        );
+        assert_eq!(users["enabled"], true);
+        let health = metadata[1].json();
+        assert_eq!(health["serviceName"], "app1");
+        assert_eq!(health["enabled"], false);
+    }
 
-        let port = &self.port;
-        let host = &self.host;
+    #[test]
+    fn test_register_blocking() {
+        let admin = FakeServer::admin();
+        let client = client(&admin);
+        client.register().unwrap();
+        assert!(admin.requests()[0].is(reqwest::Method::GET, PLATFORM_LOGIN_SUFFIX));
+        assert_eq!(admin.requests_to(REGISTER_URI_SUFFIX).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_register_blocking_inside_runtime() {
+        let admin = FakeServer::admin();
+        let client = client(&admin);
+        client.register_uri().unwrap();
+        client.offline_register().unwrap();
+        assert_eq!(admin.requests_to(REGISTER_OFFLINE_SUFFIX).len(), 1);
+    }
+
+    async fn wait_for(mut condition: impl FnMut() -> bool) {
+        for _ in 0..100 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("condition not met in time");
+    }
 
-        let json_data = serde_json::json!({
-            "appName": app_name,
-            "contextPath": context_path,
-            "protocol": rpc_type,
-            "host": host.clone().unwrap(),
-            "port": port,
-            "eventType": EventType::REGISTER.to_string(),
+    #[tokio::test]
+    async fn test_heartbeat() {
+        let admin = FakeServer::admin();
+        let mut client = client(&admin);
+        client.env.register.heartbeat = Some(HeartbeatConfig {
+            interval_ms: 50,
+            jitter_ms: 10,
+            metadata: true,
         });
+        client.register_async().await.unwrap();
+
+        wait_for(|| admin.requests_to(REGISTER_URI_SUFFIX).len() >= 3).await;
+        assert!(admin.requests_to(REGISTER_META_DATA_SUFFIX).len() >= 2);
+
+        client.offline_register_async().await.unwrap();
+        let uris = admin.requests_to(REGISTER_URI_SUFFIX).len();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(admin.requests_to(REGISTER_URI_SUFFIX).len(), uris);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_goes_on_past_failures() {
+        let admin = FakeServer::start(|req| match req.path() {
+            REGISTER_META_DATA_SUFFIX => (reqwest::StatusCode::BAD_REQUEST, "fail".to_string()),
+            _ => (reqwest::StatusCode::OK, "success".to_string()),
+        });
+        let uri_infos = vec![
+            UriInfo::new("/health", "get"),
+            UriInfo::new("/users", "post"),
+        ];
+        let client = ShenyuClient::builder()
+            .config(config(&admin.base_url))
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap();
+        client
+            .start_heartbeat(HeartbeatConfig {
+                interval_ms: 50,
+                jitter_ms: 0,
+                metadata: true,
+            })
+            .unwrap();
+
+        // the rejected metadata neither stops the URI nor the other entries
+        wait_for(|| admin.requests_to(REGISTER_URI_SUFFIX).len() >= 3).await;
+        client.stop_heartbeat();
+        let paths: Vec<_> = admin
+            .requests_to(REGISTER_META_DATA_SUFFIX)
+            .iter()
+            .map(|r| r.json()["path"].as_str().unwrap().to_string())
+            .collect();
+        assert!(paths.len() >= 6);
+        assert_eq!(paths[..2], ["/xxx/health", "/xxx/users"]);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_handle() {
+        let admin = FakeServer::admin();
+        let client = client(&admin);
+        client.register_async().await.unwrap();
+        let handle = client
+            .start_heartbeat(HeartbeatConfig {
+                interval_ms: 50,
+                jitter_ms: 0,
+                metadata: false,
+            })
+            .unwrap();
+
+        wait_for(|| admin.requests_to(REGISTER_URI_SUFFIX).len() >= 3).await;
+        assert_eq!(admin.requests_to(REGISTER_META_DATA_SUFFIX).len(), 1);
+
+        handle.stop();
+        wait_for(|| handle.is_stopped()).await;
+        let uris = admin.requests_to(REGISTER_URI_SUFFIX).len();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(admin.requests_to(REGISTER_URI_SUFFIX).len(), uris);
+    }
 
-        for url in &self.register_offline_servers {
-            if self.request(url, &json_data).unwrap() {
-                info!(
-                    "[SUCCESS], offline success, register data: {:#?}",
-                    json_data
+    /// An admin answering 503 to the first `failures` requests of every
+    /// endpoint, as while it is still starting up.
+    fn starting_admin(failures: usize) -> FakeServer {
+        let seen = Mutex::new(std::collections::HashMap::<String, usize>::new());
+        FakeServer::start(move |req| {
+            let mut seen = seen.lock().unwrap();
+            let count = seen.entry(req.path().to_string()).or_default();
+            *count += 1;
+            if *count <= failures {
+                return (
+                    reqwest::StatusCode::SERVICE_UNAVAILABLE,
+                    "starting".to_string(),
                 );
-                return;
             }
+            match req.path() {
+                PLATFORM_LOGIN_SUFFIX => (
+                    reqwest::StatusCode::OK,
+                    r#"{"code":200,"data":{"token":"fake-token"}}"#.to_string(),
+                ),
+                _ => (reqwest::StatusCode::OK, "success".to_string()),
+            }
+        })
+    }
+
+    fn retry(max_attempts: u32) -> crate::config::RetryConfig {
+        crate::config::RetryConfig {
+            max_attempts,
+            base_delay_ms: 1,
+            max_delay_ms: 10,
+            jitter_ms: 1,
+            ..Default::default()
         }
+    }
+
+    #[tokio::test]
+    async fn test_retry_while_admin_starts() {
+        let admin = starting_admin(2);
+        let mut client = client(&admin);
+        client.env.retry = retry(3);
+        client.register_async().await.unwrap();
+        assert_eq!(admin.requests_to(PLATFORM_LOGIN_SUFFIX).len(), 3);
+        assert_eq!(admin.requests_to(REGISTER_META_DATA_SUFFIX).len(), 3);
+        assert_eq!(admin.requests_to(REGISTER_URI_SUFFIX).len(), 3);
+        assert_eq!(admin.requests_to(REGISTER_DISCOVERY_CONFIG_SUFFIX).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let admin = starting_admin(2);
+        let mut client = client(&admin);
+        client.env.retry = retry(2);
+        let err = client.register_async().await.unwrap_err();
+        assert!(matches!(err, Error::Rejected { status: 503, .. }));
+        assert_eq!(admin.requests_to(PLATFORM_LOGIN_SUFFIX).len(), 2);
+        assert!(admin.requests_to(REGISTER_URI_SUFFIX).is_empty());
+    }
 
-        error!(
-            "[ERROR], register uri failed, app_name: {}, host: {}, port: {}",
-            app_name,
-            host.clone().unwrap(),
-            port
+    #[tokio::test]
+    async fn test_failover_to_next_server() {
+        let admin = FakeServer::admin();
+        // a port nothing listens on anymore
+        let down_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let down = format!("http://{}", down_listener.local_addr().unwrap());
+        drop(down_listener);
+        let uri_infos = vec![UriInfo::new("/health", "get")];
+        let servers = format!("{},{}", down, admin.base_url);
+        let mut client = ShenyuClient::builder()
+            .config(config(&servers))
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap();
+        client.env.retry = retry(1);
+        let report = client.register_async().await.unwrap();
+        assert_eq!(admin.requests_to(REGISTER_URI_SUFFIX).len(), 1);
+
+        let uri = report
+            .items
+            .iter()
+            .find(|item| item.kind == ItemKind::Uri)
+            .unwrap();
+        assert_eq!(uri.attempts.len(), 2);
+        assert!(uri.attempts[0].server.starts_with(&down));
+        assert!(!uri.attempts[0].is_success());
+        assert_eq!(uri.attempts[0].status, None);
+        let accepted = &uri.attempts[1];
+        assert_eq!(
+            accepted.server,
+            format!("{}{}", admin.base_url, REGISTER_URI_SUFFIX)
+        );
+        assert_eq!(accepted.status, Some(200));
+        assert_eq!(accepted.body.as_deref(), Some("success"));
+        assert!(accepted.latency > std::time::Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_report_lists_every_item() {
+        // the admin refuses the metadata of /broken only
+        let admin = FakeServer::start(|req| match req.path() {
+            PLATFORM_LOGIN_SUFFIX => (
+                reqwest::StatusCode::OK,
+                r#"{"code":200,"data":{"token":"fake-token"}}"#.to_string(),
+            ),
+            REGISTER_META_DATA_SUFFIX if req.json()["path"] == "/xxx/broken" => (
+                reqwest::StatusCode::BAD_REQUEST,
+                "rule name is invalid".to_string(),
+            ),
+            _ => (reqwest::StatusCode::OK, "success".to_string()),
+        });
+        let uri_infos: Vec<UriInfo> = ["/broken", "/health"]
+            .iter()
+            .map(|path| UriInfo::new(path, "get"))
+            .collect();
+        let mut client = ShenyuClient::builder()
+            .config(config(&admin.base_url))
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap();
+        client.env.retry = retry(1);
+
+        let err = client.register_async().await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "registration incomplete: 1 of 4 items failed"
+        );
+        let Error::Incomplete(report) = err else {
+            panic!("unexpected error: {err}");
+        };
+        // the routes after the failing one are registered all the same
+        let names: Vec<_> = report.items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names[..2], ["/xxx/broken", "/xxx/health"]);
+        assert_eq!(report.items[2].kind, ItemKind::Uri);
+        assert_eq!(report.items[3].kind, ItemKind::DiscoveryConfig);
+
+        let failed: Vec<_> = report.failures().collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempts[0].status, Some(400));
+        assert_eq!(
+            failed[0].attempts[0].body.as_deref(),
+            Some("rule name is invalid")
         );
+        assert!(report.to_string().contains("[FAILED] metadata /xxx/broken"));
+        assert_eq!(admin.requests_to(REGISTER_URI_SUFFIX).len(), 1);
     }
 }
@@ -0,0 +1,430 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use super::{advertised_address, ShenyuClient};
+use crate::auth::AuthStrategy;
+use crate::config::{RetryConfig, ShenYuConfig};
+use crate::error::{Error, Result};
+use crate::model::UriInfo;
+use crate::registrar::http::HttpRegistrar;
+use crate::registrar::{self, Registrar};
+use crate::{transport, IRouter};
+use dashmap::DashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Builds a [`ShenyuClient`], see [`ShenyuClient::builder`].
+///
+/// ```no_run
+/// use shenyu_client_rust::core::ShenyuClient;
+/// use shenyu_client_rust::model::UriInfo;
+///
+/// let client = ShenyuClient::builder()
+///     .config_file("config.yml")
+///     .app_name("app1")
+///     .uri_infos(&[UriInfo::new("/health", "get")])
+///     .port(8080)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct ShenyuClientBuilder {
+    config: Option<ShenYuConfig>,
+    config_file: Option<PathBuf>,
+    app_name: Option<String>,
+    uri_infos: Vec<UriInfo>,
+    /// `None` for a router serving `uri.rpc_type`
+    rpc_types: Vec<Option<String>>,
+    host: Option<String>,
+    port: Option<u16>,
+    http_client: Option<reqwest::Client>,
+    auth: Option<Box<dyn AuthStrategy>>,
+    retry: Option<RetryConfig>,
+}
+
+impl ShenyuClient {
+    /// Starts building a client.
+    pub fn builder() -> ShenyuClientBuilder {
+        ShenyuClientBuilder::default()
+    }
+}
+
+impl ShenyuClientBuilder {
+    /// Uses `config`, replacing any config file.
+    pub fn config(mut self, config: ShenYuConfig) -> Self {
+        self.config = Some(config);
+        self.config_file = None;
+        self
+    }
+
+    /// Loads the config from the file at `path`, overridden by the
+    /// `SHENYU_*` environment variables, see [`crate::config::ConfigLoader`].
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self.config = None;
+        self
+    }
+
+    /// The name registered for the app, the one of the first router or
+    /// `uri.app_name` when not set.
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// Registers the routes of `router`; may be called once per router.
+    ///
+    /// A router serving another `rpcType`, e.g. gRPC, overrides
+    /// `uri.rpc_type`; routers serving different ones can't be combined.
+    pub fn router(mut self, router: &dyn IRouter) -> Self {
+        if self.app_name.is_none() {
+            self.app_name = Some(router.app_name().to_string());
+        }
+        let rpc_type = router.rpc_type().map(str::to_string);
+        if !self.rpc_types.contains(&rpc_type) {
+            self.rpc_types.push(rpc_type);
+        }
+        self.uri_infos.extend_from_slice(router.uri_infos());
+        self
+    }
+
+    /// Registers `uri_infos` along with the routes of the routers.
+    pub fn uri_infos(mut self, uri_infos: &[UriInfo]) -> Self {
+        self.uri_infos.extend_from_slice(uri_infos);
+        self
+    }
+
+    /// Registers the routes of every `#[shenyu_client]` handler, see
+    /// [`crate::macros`]; not to be combined with the routers serving them.
+    #[cfg(feature = "macros")]
+    pub fn registered_routes(self) -> Self {
+        self.uri_infos(&crate::macros::uri_infos())
+    }
+
+    /// Advertises `host` instead of `uri.host`.
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Advertises `port` instead of `uri.port`.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Sends the calls to the register center with `http_client` instead of
+    /// one built from the `http` section and `register.tls`. The servers are
+    /// still called by `register.tls.server_name` when set, which
+    /// `http_client` has to resolve.
+    pub fn http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Authenticates to the admin with `auth` instead of the strategy
+    /// selected by `register.props.authType`; `http` register center only.
+    pub fn auth(mut self, auth: impl AuthStrategy + 'static) -> Self {
+        self.auth = Some(Box::new(auth));
+        self
+    }
+
+    /// Retries as told by `retry` instead of the `retry` section.
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Builds the client, once the config and the options pass
+    /// [`ShenYuConfig::validate`].
+    pub fn build(self) -> Result<ShenyuClient> {
+        self.build_with(true)
+    }
+
+    /// Builds the client without validating the config, as the constructors
+    /// preceding the builder did.
+    pub(crate) fn build_unchecked(self) -> Result<ShenyuClient> {
+        self.build_with(false)
+    }
+
+    fn build_with(self, validate: bool) -> Result<ShenyuClient> {
+        let mut config = match (self.config, self.config_file) {
+            (Some(config), _) => config,
+            (None, Some(path)) => ShenYuConfig::loader().file(path).env().load()?,
+            (None, None) => {
+                return Err(Error::Config(
+                    "no config, set one with config() or config_file()".to_string(),
+                ))
+            }
+        };
+        let app_name = self.app_name.unwrap_or_else(|| config.uri.app_name.clone());
+        config.uri.app_name.clone_from(&app_name);
+        if let Some(retry) = self.retry {
+            config.retry = retry;
+        }
+        let mut rpc_types: Vec<String> = Vec::new();
+        for rpc_type in self.rpc_types {
+            let rpc_type = rpc_type.unwrap_or_else(|| config.uri.rpc_type.clone());
+            if !rpc_types.contains(&rpc_type) {
+                rpc_types.push(rpc_type);
+            }
+        }
+        match rpc_types.as_slice() {
+            [] => {}
+            [rpc_type] => config.uri.rpc_type.clone_from(rpc_type),
+            rpc_types => {
+                return Err(Error::Config(format!(
+                    "the routers serve different rpc types: {}",
+                    rpc_types.join(", ")
+                )))
+            }
+        }
+        if validate {
+            config.check(self.auth.is_none())?;
+        }
+        let (host, port) = advertised_address(&config.uri, self.host, self.port)?;
+
+        let headers = Arc::new(DashMap::new());
+        headers.insert(
+            "Content-Type".to_string(),
+            "application/json;charset=UTF-8".to_string(),
+        );
+        let http_client = match self.http_client {
+            Some(http_client) => {
+                transport::pinned_server(&config.register)?;
+                http_client
+            }
+            None => transport::http_client(&config)?,
+        };
+        let registrar: Box<dyn Registrar> = match self.auth {
+            None => registrar::from_config(&config, http_client.clone(), headers.clone())?,
+            Some(auth) if config.register.register_type == "http" => Box::new(
+                HttpRegistrar::new(&config.register, http_client.clone(), headers.clone())?
+                    .with_auth(auth),
+            ),
+            Some(_) => {
+                return Err(Error::Config(format!(
+                    "an auth strategy only applies to the http register center, not {}",
+                    config.register.register_type
+                )))
+            }
+        };
+        Ok(ShenyuClient {
+            headers,
+            http_client,
+            registrar: registrar.into(),
+            heartbeat: Mutex::new(None),
+            app_name,
+            env: config,
+            host,
+            port,
+            uri_infos: self.uri_infos,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::REGISTER_URI_SUFFIX;
+    use crate::test_support::{config, FakeServer};
+    use reqwest::RequestBuilder;
+
+    struct Router(Vec<UriInfo>);
+
+    impl IRouter for Router {
+        fn app_name(&self) -> &str {
+            "router-app"
+        }
+
+        fn uri_infos(&self) -> &Vec<UriInfo> {
+            &self.0
+        }
+    }
+
+    fn uri_info(path: &str) -> UriInfo {
+        UriInfo::new(path, "get")
+    }
+
+    #[derive(Debug)]
+    struct ApiKey;
+
+    impl AuthStrategy for ApiKey {
+        fn sign(&self, builder: RequestBuilder, _body: &[u8]) -> Result<RequestBuilder> {
+            Ok(builder.header("X-Api-Key", "k1"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build() {
+        let admin = FakeServer::admin();
+        let mut config = config(&admin.base_url);
+        config.register.props.clear();
+        let client = ShenyuClient::builder()
+            .config(config)
+            .router(&Router(vec![uri_info("/health")]))
+            .router(&Router(vec![uri_info("/users")]))
+            .host("10.0.0.7")
+            .port(8080)
+            .auth(ApiKey)
+            .retry(RetryConfig {
+                max_attempts: 1,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        assert_eq!(client.app_name, "router-app");
+        assert_eq!(client.uri_infos.len(), 2);
+        assert_eq!(client.env.retry.max_attempts, 1);
+        client.register_uri_async().await.unwrap();
+
+        let uri = &admin.requests_to(REGISTER_URI_SUFFIX)[0];
+        assert_eq!(uri.headers["X-Api-Key"], "k1");
+        assert_eq!(uri.json()["appName"], "router-app");
+        assert_eq!(uri.json()["host"], "10.0.0.7");
+        assert_eq!(uri.json()["port"], 8080);
+    }
+
+    #[test]
+    fn test_build_validates() {
+        let err = ShenyuClient::builder().port(8080).build().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: no config, set one with config() or config_file()"
+        );
+
+        let mut anonymous = config("http://127.0.0.1:9095");
+        anonymous.register.props.clear();
+        anonymous.uri.app_name.clear();
+        let err = ShenyuClient::builder()
+            .config(anonymous)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.props.username is required to log in; \
+             register.props.password is required to log in; \
+             uri.app_name is empty"
+        );
+
+        let mut eureka = config("http://127.0.0.1:9095");
+        eureka.register.register_type = "eureka".to_string();
+        let err = ShenyuClient::builder()
+            .config(eureka)
+            .port(8080)
+            .build()
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid config: register.register_type: unsupported eureka"));
+
+        let mut zookeeper = config("127.0.0.1:2181");
+        zookeeper.register.register_type = "zookeeper".to_string();
+        let err = ShenyuClient::builder()
+            .config(zookeeper)
+            .port(8080)
+            .auth(ApiKey)
+            .build()
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("only applies to the http register center"));
+    }
+
+    #[test]
+    fn test_build_rpc_types() {
+        struct Grpc;
+
+        impl IRouter for Grpc {
+            fn app_name(&self) -> &str {
+                "grpc-app"
+            }
+
+            fn uri_infos(&self) -> &Vec<UriInfo> {
+                const NONE: &Vec<UriInfo> = &Vec::new();
+                NONE
+            }
+
+            fn rpc_type(&self) -> Option<&str> {
+                Some("grpc")
+            }
+        }
+
+        let springcloud = || {
+            let mut config = config("http://127.0.0.1:9095");
+            config.uri.rpc_type = "springCloud".to_string();
+            config
+        };
+        let client = ShenyuClient::builder()
+            .config(springcloud())
+            .router(&Router(vec![uri_info("/health")]))
+            .port(8080)
+            .build()
+            .unwrap();
+        assert_eq!(client.env.uri.rpc_type, "springCloud");
+
+        let client = ShenyuClient::builder()
+            .config(springcloud())
+            .router(&Grpc)
+            .router(&Grpc)
+            .port(8080)
+            .build()
+            .unwrap();
+        assert_eq!(client.env.uri.rpc_type, "grpc");
+
+        struct Websocket;
+
+        impl IRouter for Websocket {
+            fn app_name(&self) -> &str {
+                "ws-app"
+            }
+
+            fn uri_infos(&self) -> &Vec<UriInfo> {
+                Grpc.uri_infos()
+            }
+
+            fn rpc_type(&self) -> Option<&str> {
+                Some("websocket")
+            }
+        }
+
+        let err = ShenyuClient::builder()
+            .config(springcloud())
+            .router(&Grpc)
+            .router(&Websocket)
+            .port(8080)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: the routers serve different rpc types: grpc, websocket"
+        );
+
+        // an http router and a grpc one can't share the URI either
+        let err = ShenyuClient::builder()
+            .config(config("http://127.0.0.1:9095"))
+            .router(&Router(vec![uri_info("/health")]))
+            .router(&Grpc)
+            .port(8080)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: the routers serve different rpc types: http, grpc"
+        );
+    }
+}
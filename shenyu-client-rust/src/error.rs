@@ -14,23 +14,62 @@
 // KIND, either express or implied.  See the License for the
 // specific language governing permissions and limitations
 // under the License.
+//! The error type returned by every fallible API of the client.
 
-use std::io::Error;
+use crate::report::RegistrationReport;
 
-#[derive(Debug)]
-pub struct ShenYuError {
-    pub code: i32,
-    pub message: String,
+/// Everything that can go wrong while configuring the client or talking to
+/// the register center.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The configuration is missing, can't be read or holds invalid values.
+    #[error("invalid config: {0}")]
+    Config(String),
+    /// `register.register_type` names a register center this crate doesn't
+    /// ship.
+    #[error("Unsupported register type: {0}")]
+    UnsupportedRegisterType(String),
+    /// No address to advertise could be determined for this instance.
+    #[error("Failed to determine local IP address: {0}")]
+    NoLocalAddress(String),
+    /// The register center could not be reached.
+    #[error("{target}: {message}")]
+    Transport { target: String, message: String },
+    /// Logging in to the register center failed.
+    #[error("authentication failed: {0}")]
+    Auth(String),
+    /// The register center answered but refused the call.
+    #[error("rejected with status {status}: {body}")]
+    Rejected { status: u16, body: String },
+    /// A payload could not be encoded or a response could not be decoded.
+    #[error("invalid data: {0}")]
+    InvalidData(String),
+    /// Some items could not be registered; the report tells which and why.
+    #[error(
+        "registration incomplete: {} of {} items failed",
+        .0.failures().count(),
+        .0.items.len()
+    )]
+    Incomplete(Box<RegistrationReport>),
+    /// The runtime driving the blocking API or background tasks failed.
+    #[error("runtime error: {0}")]
+    Runtime(String),
 }
 
-impl ShenYuError {
-    pub fn new(code: i32, message: String) -> Self {
-        ShenYuError { code, message }
+/// `Result` with the client's [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    pub(crate) fn transport(target: impl Into<String>, e: impl std::fmt::Display) -> Self {
+        Error::Transport {
+            target: target.into(),
+            message: e.to_string(),
+        }
     }
 }
 
-impl From<ShenYuError> for Error {
-    fn from(val: ShenYuError) -> Self {
-        Error::new(std::io::ErrorKind::Other, val.message)
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::InvalidData(e.to_string())
     }
 }
@@ -0,0 +1,130 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The background re-registration loop, see [`HeartbeatConfig`].
+
+use crate::config::HeartbeatConfig;
+use crate::error::{Error, Result};
+use crate::model::{MetaDataRegister, UriRegister};
+use crate::registrar::Registrar;
+use crate::report::{ItemKind, ItemReport, RegistrationReport};
+use crate::{network, rt};
+use rand::Rng;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::AbortHandle;
+use tracing::{debug, warn};
+
+/// Stops a heartbeat started by [`crate::core::ShenyuClient::start_heartbeat`].
+///
+/// Dropping the handle leaves the heartbeat running; it stops with
+/// [`HeartbeatHandle::stop`] or when the client goes offline.
+#[derive(Debug, Clone)]
+pub struct HeartbeatHandle {
+    abort: AbortHandle,
+}
+
+impl HeartbeatHandle {
+    pub fn stop(&self) {
+        self.abort.abort();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.abort.is_finished()
+    }
+}
+
+/// Spawns the loop re-sending `uri` (and `metadata`, when enabled) every
+/// `config.interval_ms` plus jitter.
+pub(crate) fn spawn(
+    registrar: Arc<dyn Registrar>,
+    config: HeartbeatConfig,
+    uri: UriRegister,
+    metadata: Vec<MetaDataRegister>,
+) -> Result<HeartbeatHandle> {
+    let handle = rt::spawn(async move {
+        loop {
+            tokio::time::sleep(delay(&config)).await;
+            // calls refused for their credentials were already sent again with
+            // renewed ones; logging in anew covers the other failures, such as
+            // the register center restarting
+            if let Err(e) = beat(registrar.as_ref(), &config, &uri, &metadata).await {
+                match &e {
+                    Error::Incomplete(report) => {
+                        warn!(
+                            "Heartbeat failed, logging in again before the next round: {}\n{}",
+                            e, report
+                        )
+                    }
+                    e => warn!(
+                        "Heartbeat failed, logging in again before the next round: {}",
+                        e
+                    ),
+                }
+                if let Err(e) = registrar.login().await {
+                    warn!("Heartbeat login failed: {}", e);
+                }
+            }
+        }
+    })?;
+    Ok(HeartbeatHandle {
+        abort: handle.abort_handle(),
+    })
+}
+
+fn delay(config: &HeartbeatConfig) -> Duration {
+    let jitter = match config.jitter_ms {
+        0 => 0,
+        jitter => rand::thread_rng().gen_range(0..=jitter),
+    };
+    Duration::from_millis(config.interval_ms + jitter)
+}
+
+/// Sends every metadata entry (when enabled) then the URI, going on past
+/// the ones that fail; see [`crate::error::Error::Incomplete`].
+async fn beat(
+    registrar: &dyn Registrar,
+    config: &HeartbeatConfig,
+    uri: &UriRegister,
+    metadata: &[MetaDataRegister],
+) -> Result<()> {
+    let mut report = RegistrationReport::default();
+    if config.metadata {
+        for metadata in metadata {
+            let mut attempts = Vec::new();
+            let res = registrar.register_metadata(metadata, &mut attempts).await;
+            report.push(ItemReport {
+                kind: ItemKind::Metadata,
+                name: metadata.path.clone(),
+                attempts,
+                error: res.err(),
+            });
+        }
+    }
+    let name = network::host_port(&uri.host, uri.port);
+    let mut attempts = Vec::new();
+    let res = registrar.register_uri(uri, &mut attempts).await;
+    report.push(ItemReport {
+        kind: ItemKind::Uri,
+        name: name.clone(),
+        attempts,
+        error: res.err(),
+    });
+    report.into_result()?;
+    debug!("Heartbeat of {} sent", name);
+    Ok(())
+}
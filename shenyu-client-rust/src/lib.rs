@@ -17,18 +17,39 @@
 
 //! Rust shenyu-client-rust sdk of Apache ShenYu.
 
+// lets `#[shenyu_client]` name this crate from within it
+#[cfg(feature = "macros")]
+extern crate self as shenyu_client_rust;
+
 use crate::model::UriInfo;
 
+pub mod auth;
 pub mod config;
 pub mod core;
 pub mod error;
+pub mod heartbeat;
 pub mod macros;
 pub mod model;
+mod network;
+pub mod registrar;
+pub mod report;
+mod retry;
+mod rt;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "tonic")]
+pub mod tonic_impl;
+mod transport;
 
 pub trait IRouter {
     fn app_name(&self) -> &str;
 
     fn uri_infos(&self) -> &Vec<UriInfo>;
+
+    /// The `rpcType` the routes are served with, `uri.rpc_type` when `None`.
+    fn rpc_type(&self) -> Option<&str> {
+        None
+    }
 }
 
 #[cfg(feature = "axum")]
@@ -103,15 +124,56 @@ pub mod axum_impl {
 
         pub fn route(mut self, path: &str, method: &str, method_router: MethodRouter<S>) -> Self {
             self.inner = self.inner.route(path, method_router);
-            self.uri_infos.push(UriInfo {
-                path: path.to_string(),
-                rule_name: path.to_string(),
-                service_name: None,
-                method_name: method.to_string(),
-            });
+            self.uri_infos.push(UriInfo::new(path, method));
             self
         }
 
+        /// Like [`ShenYuRouter::route`], registering the metadata of
+        /// `uri_info`, e.g. its description or plugins.
+        ///
+        /// ```rust
+        /// use axum::routing::post;
+        /// use shenyu_client_rust::axum_impl::ShenYuRouter;
+        /// use shenyu_client_rust::model::UriInfo;
+        ///
+        /// async fn create_user_handler() -> &'static str {
+        ///     "User created"
+        /// }
+        ///
+        /// let app = ShenYuRouter::<()>::new("shenyu_client_app").route_with(
+        ///     UriInfo::new("/users", "post")
+        ///         .description("Creates a user")
+        ///         .plugin_names(["rateLimiter"]),
+        ///     post(create_user_handler),
+        /// );
+        /// ```
+        pub fn route_with(mut self, uri_info: UriInfo, method_router: MethodRouter<S>) -> Self {
+            self.inner = self.inner.route(&uri_info.path, method_router);
+            self.uri_infos.push(uri_info);
+            self
+        }
+
+        /// Routes to a `#[shenyu_client]` handler, see
+        /// [`shenyu_route!`](crate::shenyu_route).
+        ///
+        /// # Panics
+        ///
+        /// When the method of the route is not an HTTP method.
+        #[cfg(feature = "macros")]
+        #[track_caller]
+        pub fn handler<H, T>(self, route: crate::macros::Route<H>) -> Self
+        where
+            H: axum::handler::Handler<T, S>,
+            T: 'static,
+        {
+            let method = route.uri_info.method_name.to_uppercase();
+            let filter = axum::http::Method::from_bytes(method.as_bytes())
+                .ok()
+                .and_then(|method| axum::routing::MethodFilter::try_from(method).ok())
+                .unwrap_or_else(|| panic!("{method} is not an HTTP method"));
+            self.route_with(route.uri_info, axum::routing::on(filter, route.handler))
+        }
+
         pub fn route_service<T>(mut self, path: &str, method: &str, service: T) -> Self
         where
             T: Service<Request, Error = Infallible> + Clone + Send + 'static,
@@ -119,12 +181,7 @@ pub mod axum_impl {
             T::Future: Send + 'static,
         {
             self.inner = self.inner.route_service(path, service);
-            self.uri_infos.push(UriInfo {
-                path: path.to_string(),
-                rule_name: path.to_string(),
-                service_name: None,
-                method_name: method.to_string(),
-            });
+            self.uri_infos.push(UriInfo::new(path, method));
             self
         }
 
@@ -143,12 +200,7 @@ pub mod axum_impl {
             T::Future: Send + 'static,
         {
             self.inner = self.inner.nest_service(path, service);
-            self.uri_infos.push(UriInfo {
-                path: path.to_string(),
-                rule_name: path.to_string(),
-                service_name: None,
-                method_name: method.to_string(),
-            });
+            self.uri_infos.push(UriInfo::new(path, method));
             self
         }
 
@@ -190,17 +242,29 @@ pub mod axum_impl {
 }
 
 impl ShenyuClient {
-    pub fn parse(path: &str, router: Box<dyn IRouter>, port: u16) -> Result<Self, String> {
-        let config = ShenYuConfig::from_yaml_file(path).unwrap();
-        Self::from(config, router.app_name(), router.uri_infos(), port)
+    /// Builds a client from the config file at `path`, overridden by the
+    /// `SHENYU_*` environment variables, see [`config::ConfigLoader`].
+    #[deprecated(
+        since = "0.1.2",
+        note = "use `ShenyuClient::builder().config_file(path).router(&router)` instead"
+    )]
+    pub fn parse(path: &str, router: Box<dyn IRouter>, port: u16) -> Result<Self> {
+        let mut config = ShenYuConfig::loader().file(path).env().load()?;
+        config.uri.port.get_or_insert(port);
+        Self::builder()
+            .config(config)
+            .router(&*router)
+            .build_unchecked()
     }
 
+    #[deprecated(since = "0.1.2", note = "use `ShenyuClient::builder()` instead")]
+    #[allow(deprecated)]
     pub fn from(
         config: ShenYuConfig,
         app_name: &str,
         uri_infos: &[UriInfo],
         port: u16,
-    ) -> Result<Self, String> {
+    ) -> Result<Self> {
         Self::new(config, app_name, uri_infos, port)
     }
 }
@@ -208,6 +272,8 @@ impl ShenyuClient {
 #[cfg(feature = "actix-web")]
 pub mod actix_web_impl {
     use super::model::UriInfo;
+    use crate::config::ShenYuConfig;
+    use crate::core::ShenyuClient;
     use crate::IRouter;
 
     /// A router that can be used to register routes.
@@ -234,12 +300,36 @@ pub mod actix_web_impl {
         }
 
         pub fn route(&mut self, path: &str, method: &str) {
-            self.uri_infos.push(UriInfo {
-                path: path.to_string().clone(),
-                rule_name: path.to_string().clone(),
-                service_name: None,
-                method_name: method.to_string(),
-            });
+            self.uri_infos.push(UriInfo::new(path, method));
+        }
+
+        /// Like [`ShenYuRouter::route`], registering the metadata of
+        /// `uri_info`, e.g. its description or plugins.
+        pub fn route_with(&mut self, uri_info: UriInfo) {
+            self.uri_infos.push(uri_info);
+        }
+
+        /// The resource serving a `#[shenyu_client]` handler, to be added to
+        /// the app with `App::service`; see [`shenyu_route!`](crate::shenyu_route).
+        ///
+        /// # Panics
+        ///
+        /// When the method of the route is not an HTTP method.
+        #[cfg(feature = "macros")]
+        #[track_caller]
+        pub fn handler<F, Args>(&mut self, route: crate::macros::Route<F>) -> actix_web::Resource
+        where
+            F: actix_web::Handler<Args>,
+            Args: actix_web::FromRequest + 'static,
+            F::Output: actix_web::Responder + 'static,
+        {
+            let method = route.uri_info.method_name.to_uppercase();
+            let method = actix_web::http::Method::from_bytes(method.as_bytes())
+                .unwrap_or_else(|_| panic!("{method} is not an HTTP method"));
+            let resource = actix_web::web::resource(&route.uri_info.path)
+                .route(actix_web::web::method(method).to(route.handler));
+            self.route_with(route.uri_info);
+            resource
         }
     }
 
@@ -253,10 +343,45 @@ pub mod actix_web_impl {
         }
     }
 
+    /// Registers from a task spawned on the actix runtime and goes offline on
+    /// `ctrl_c`; failures are logged. Used by [`register_once!`].
+    #[doc(hidden)]
+    pub fn register_in_background(
+        mut config: ShenYuConfig,
+        app_name: &str,
+        uri_infos: &[UriInfo],
+        port: u16,
+    ) {
+        config.uri.port.get_or_insert(port);
+        let client = ShenyuClient::builder()
+            .config(config)
+            .app_name(app_name)
+            .uri_infos(uri_infos)
+            .build();
+        let client = match client {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("[ERROR], failed to create the shenyu client: {}", e);
+                return;
+            }
+        };
+        actix_web::rt::spawn(async move {
+            if client.register_async().await.is_err() {
+                return;
+            }
+            // Add shutdown hook
+            if actix_web::rt::signal::ctrl_c().await.is_ok() {
+                // failures are logged by the client
+                let _ = client.offline_register_async().await;
+            }
+        });
+    }
+
     /// Macro to register the ShenYu client once.
     ///
     /// This macro ensures that the ShenYu client is registered only once using a `OnceLock`.
-    /// It initializes the client with the provided configuration, router, and port, and sets up
+    /// It initializes the client with the provided configuration, router, and port, registers it
+    /// from a spawned task so the worker thread is not blocked, and sets up
     /// a shutdown hook to deregister the client upon receiving a `ctrl_c` signal.
     ///
     /// # Arguments
@@ -272,25 +397,12 @@ pub mod actix_web_impl {
 
             static ONCE: OnceLock<()> = OnceLock::new();
             ONCE.get_or_init(|| {
-                let client = {
-                    let res = $crate::core::ShenyuClient::from(
-                        $config,
-                        $router.app_name(),
-                        $router.uri_infos(),
-                        $port,
-                    );
-                    let client = res.unwrap();
-                    client
-                };
-                client.register().expect("Failed to register");
-                actix_web::rt::spawn(async move {
-                    // Add shutdown hook
-                    tokio::select! {
-                        _ = actix_web::rt::signal::ctrl_c() => {
-                            client.offline_register();
-                        }
-                    }
-                });
+                $crate::actix_web_impl::register_in_background(
+                    $config,
+                    $router.app_name(),
+                    $router.uri_infos(),
+                    $port,
+                );
             });
         };
     }
@@ -321,14 +433,13 @@ pub mod actix_web_impl {
 
 use crate::config::ShenYuConfig;
 use crate::core::ShenyuClient;
+use crate::error::Result;
 
 #[cfg(test)]
 #[cfg(feature = "axum")]
 mod tests_axum {
     use super::axum_impl::ShenYuRouter;
-    use crate::config::ShenYuConfig;
     use crate::core::ShenyuClient;
-    use crate::IRouter;
     use axum::routing::{get, post};
     use serde_json::Value;
     use std::collections::HashMap;
@@ -367,8 +478,11 @@ mod tests_axum {
             .nest("/api", ShenYuRouter::new("api"))
             .route("/health", "get", get(health_handler))
             .route("/users", "post", post(create_user_handler));
-        let config = ShenYuConfig::from_yaml_file("config.yml").unwrap();
-        let res = ShenyuClient::from(config, app.app_name(), app.uri_infos(), 9527);
+        let res = ShenyuClient::builder()
+            .config_file("config.yml")
+            .router(&app)
+            .port(9527)
+            .build();
         assert!(&res.is_ok());
         let client = &mut res.unwrap();
         println!(
@@ -393,7 +507,8 @@ mod tests_axum {
         assert!(res.is_ok());
         let res = client.register_discovery_config();
         assert!(res.is_ok());
-        client.offline_register();
+        let res = client.offline_register();
+        assert!(res.is_ok());
     }
 
     #[test]
@@ -414,15 +529,16 @@ mod tests_axum {
 #[cfg(feature = "actix-web")]
 mod tests_actix_web {
     use super::actix_web_impl::ShenYuRouter;
-    use crate::config::ShenYuConfig;
     use crate::core::ShenyuClient;
-    use crate::IRouter;
 
     #[tokio::test]
     async fn build_client() {
         let app = ShenYuRouter::new("shenyu_client_app");
-        let config = ShenYuConfig::from_yaml_file("config.yml").unwrap();
-        let res = ShenyuClient::from(config, app.app_name(), app.uri_infos(), 9527);
+        let res = ShenyuClient::builder()
+            .config_file("config.yml")
+            .router(&app)
+            .port(9527)
+            .build();
         assert!(&res.is_ok());
         let client = &mut res.unwrap();
         println!(
@@ -447,6 +563,7 @@ mod tests_actix_web {
         assert!(res.is_ok());
         let res = client.register_discovery_config();
         assert!(res.is_ok());
-        client.offline_register();
+        let res = client.offline_register();
+        assert!(res.is_ok());
     }
 }
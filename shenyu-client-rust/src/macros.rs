@@ -14,3 +14,137 @@
 // KIND, either express or implied.  See the License for the
 // specific language governing permissions and limitations
 // under the License.
+
+//! Routes declared with `#[shenyu_client]` on their handler, behind the
+//! `macros` feature.
+//!
+//! ```ignore
+//! use shenyu_client_rust::macros::shenyu_client;
+//! use shenyu_client_rust::shenyu_route;
+//!
+//! #[shenyu_client(path = "/users", method = "post", desc = "Creates a user")]
+//! async fn create_user() -> &'static str {
+//!     "User created"
+//! }
+//!
+//! let app = ShenYuRouter::<()>::new("app").handler(shenyu_route!(create_user));
+//! ```
+
+#![cfg(feature = "macros")]
+
+use crate::model::UriInfo;
+
+pub use shenyu_client_rust_macros::shenyu_client;
+
+/// The route a `#[shenyu_client]` handler is registered for, implemented by
+/// the macro on a type named after the handler.
+pub trait ShenyuRoute {
+    fn uri_info() -> UriInfo;
+}
+
+/// A handler along with its route, see [`crate::shenyu_route`].
+#[derive(Debug, Clone)]
+pub struct Route<H> {
+    pub uri_info: UriInfo,
+    pub handler: H,
+}
+
+/// An entry of the registry, submitted by `#[shenyu_client]`.
+#[doc(hidden)]
+pub struct Registration {
+    uri_info: fn() -> UriInfo,
+}
+
+impl Registration {
+    pub const fn new(uri_info: fn() -> UriInfo) -> Self {
+        Self { uri_info }
+    }
+}
+
+inventory::collect!(Registration);
+
+/// The routes of every `#[shenyu_client]` handler linked in, ordered by path
+/// then method.
+pub fn uri_infos() -> Vec<UriInfo> {
+    let mut uri_infos: Vec<UriInfo> = inventory::iter::<Registration>
+        .into_iter()
+        .map(|registration| (registration.uri_info)())
+        .collect();
+    uri_infos.sort_by(|a, b| (&a.path, &a.method_name).cmp(&(&b.path, &b.method_name)));
+    uri_infos
+}
+
+/// Pairs a `#[shenyu_client]` handler with its route, for the
+/// `ShenYuRouter::handler` of axum or actix-web.
+#[macro_export]
+macro_rules! shenyu_route {
+    ($handler:path) => {
+        $crate::macros::Route {
+            uri_info: <$handler as $crate::macros::ShenyuRoute>::uri_info(),
+            handler: $handler,
+        }
+    };
+}
+
+// Not public API, used by `#[shenyu_client]`.
+#[doc(hidden)]
+pub mod __private {
+    pub use inventory;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[shenyu_client(path = "/users", method = "post", desc = "Creates a user")]
+    async fn create_user() -> &'static str {
+        "User created"
+    }
+
+    #[shenyu_client(
+        path = "/users/{id}",
+        method = "get",
+        rule_name = "/users/**",
+        service_name = "UserController",
+        plugin_names = ["rateLimiter"],
+        enabled = false
+    )]
+    async fn user() -> &'static str {
+        "User"
+    }
+
+    #[tokio::test]
+    async fn test_registry() {
+        let route = crate::shenyu_route!(create_user);
+        assert_eq!(route.uri_info.rule_name, "/users");
+        assert_eq!((route.handler)().await, "User created");
+
+        let route = crate::shenyu_route!(user);
+        assert_eq!(route.uri_info.rule_name, "/users/**");
+        assert_eq!(
+            route.uri_info.service_name.as_deref(),
+            Some("UserController")
+        );
+        assert_eq!(route.uri_info.plugin_names, ["rateLimiter"]);
+        assert!(!route.uri_info.enabled);
+
+        let uri_infos = uri_infos();
+        let routes: Vec<_> = uri_infos
+            .iter()
+            .map(|x| {
+                (
+                    x.path.as_str(),
+                    x.method_name.as_str(),
+                    x.description.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                ("/users", "post", "Creates a user"),
+                ("/users/{id}", "get", "")
+            ]
+        );
+    }
+}
@@ -15,6 +15,8 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
@@ -22,6 +24,9 @@ pub struct MetaInfo {
     pub path: String,
 }
 
+/// A route, registered as the metadata of one ShenYu rule, e.g.
+///
+/// ```json
 /// {
 /// "appName":"springCloud-test",
 /// "contextPath":"/springcloud",
@@ -38,13 +43,134 @@ pub struct MetaInfo {
 /// "timeMillis":1724062308618,
 /// "addPrefixed":false
 /// }
-
+/// ```
 #[derive(Debug, Clone)]
 pub struct UriInfo {
     pub path: String,
     pub rule_name: String,
+    /// The `serviceName`, the app name when not set.
     pub service_name: Option<String>,
     pub method_name: String,
+    /// The `pathDesc` shown by the admin.
+    pub description: String,
+    /// The `parameterTypes`, comma separated, e.g. `java.lang.String,int`.
+    pub parameter_types: String,
+    /// The `rpcExt`, a JSON document specific to the `rpcType`.
+    pub rpc_ext: String,
+    /// The plugins the admin enables for the rule.
+    pub plugin_names: Vec<String>,
+    /// Whether the rule is enabled once registered.
+    pub enabled: bool,
+}
+
+impl Default for UriInfo {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            rule_name: String::new(),
+            service_name: None,
+            method_name: String::new(),
+            description: String::new(),
+            parameter_types: String::new(),
+            rpc_ext: String::new(),
+            plugin_names: Vec::new(),
+            enabled: true,
+        }
+    }
+}
+
+impl UriInfo {
+    /// The route `method path`, `path` being its rule name as well.
+    pub fn new(path: &str, method_name: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            rule_name: path.to_string(),
+            method_name: method_name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn service_name(mut self, service_name: &str) -> Self {
+        self.service_name = Some(service_name.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn parameter_types(mut self, parameter_types: &str) -> Self {
+        self.parameter_types = parameter_types.to_string();
+        self
+    }
+
+    pub fn rpc_ext(mut self, rpc_ext: &str) -> Self {
+        self.rpc_ext = rpc_ext.to_string();
+        self
+    }
+
+    pub fn plugin_names<I, T>(mut self, plugin_names: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.plugin_names = plugin_names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+/// The metadata of one route, as registered to the register center.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaDataRegister {
+    pub app_name: String,
+    pub context_path: String,
+    pub path: String,
+    pub path_desc: String,
+    pub rpc_type: String,
+    pub rule_name: String,
+    pub service_name: String,
+    pub method_name: String,
+    pub parameter_types: String,
+    pub rpc_ext: String,
+    pub host: String,
+    pub port: u16,
+    pub enabled: bool,
+    pub register_meta_data: bool,
+    pub plugin_names: Vec<String>,
+}
+
+/// The address of this instance, as registered to the register center.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UriRegister {
+    pub app_name: String,
+    pub context_path: String,
+    pub protocol: String,
+    pub rpc_type: String,
+    pub host: String,
+    pub port: u16,
+    pub event_type: String,
+}
+
+/// The discovery config of this application's selector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryConfigRegister {
+    pub name: String,
+    pub selector_name: String,
+    pub handler: String,
+    pub listener_node: String,
+    pub server_list: String,
+    pub props: HashMap<String, String>,
+    pub discovery_type: String,
+    pub plugin_name: String,
 }
 
 pub enum EventType {
@@ -0,0 +1,160 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The address of this host advertised to the register center.
+
+use crate::config::{IpFamily, NetworkConfig};
+use crate::error::{Error, Result};
+use std::net::IpAddr;
+
+/// Picks the address to advertise among those of the network interfaces, as
+/// told by `config`.
+pub(crate) fn local_host(config: &NetworkConfig) -> Result<IpAddr> {
+    let interfaces = local_ip_address::list_afinet_netifas()
+        .map_err(|e| Error::NoLocalAddress(e.to_string()))?;
+    // the address of the default route, when it can be told
+    let default = local_ip_address::local_ip().ok();
+    pick(&interfaces, config, default)
+}
+
+/// The first address of `interfaces` allowed by `config`, of the preferred
+/// family first, `default` first within its family.
+fn pick(
+    interfaces: &[(String, IpAddr)],
+    config: &NetworkConfig,
+    default: Option<IpAddr>,
+) -> Result<IpAddr> {
+    let mut candidates: Vec<IpAddr> = interfaces
+        .iter()
+        .filter(|(name, ip)| match &config.interface {
+            Some(interface) => name == interface,
+            None => routable(ip),
+        })
+        .map(|(_, ip)| *ip)
+        .filter(|ip| config.allow.is_empty() || config.allow.iter().any(|net| net.contains(ip)))
+        .filter(|ip| !config.deny.iter().any(|net| net.contains(ip)))
+        .collect();
+    // stable, so the interfaces keep their order otherwise
+    candidates.sort_by_key(|ip| {
+        let preferred = matches!(
+            (config.prefer, ip),
+            (IpFamily::Ipv4, IpAddr::V4(_)) | (IpFamily::Ipv6, IpAddr::V6(_))
+        );
+        (!preferred, Some(*ip) != default)
+    });
+    candidates.first().copied().ok_or_else(|| {
+        Error::NoLocalAddress(format!(
+            "no address of {} matches uri.network",
+            match &config.interface {
+                Some(interface) => format!("interface {interface}"),
+                None => "the network interfaces".to_string(),
+            }
+        ))
+    })
+}
+
+/// Whether `ip` can be reached from another host without a zone.
+fn routable(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || ip.is_link_local())
+        }
+        IpAddr::V6(ip) => {
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.segments()[0] & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// `host:port`, with `host` in brackets when it is an IPv6 address, as in a
+/// URL.
+pub(crate) fn host_port(host: &str, port: u16) -> String {
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(_)) => format!("[{host}]:{port}"),
+        _ => format!("{host}:{port}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interfaces() -> Vec<(String, IpAddr)> {
+        [
+            ("lo", "127.0.0.1"),
+            ("lo", "::1"),
+            ("docker0", "172.17.0.1"),
+            ("eth0", "fe80::1"),
+            ("eth0", "10.0.5.7"),
+            ("eth0", "fd00::7"),
+            ("eth1", "192.168.1.7"),
+        ]
+        .into_iter()
+        .map(|(name, ip)| (name.to_string(), ip.parse().unwrap()))
+        .collect()
+    }
+
+    fn pick_with(yaml: &str, default: Option<&str>) -> Result<IpAddr> {
+        let config: NetworkConfig = serde_yaml::from_str(yaml).unwrap();
+        pick(
+            &interfaces(),
+            &config,
+            default.map(|ip| ip.parse().unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_pick() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert_eq!(pick_with("{}", None).unwrap(), ip("172.17.0.1"));
+        assert_eq!(pick_with("{}", Some("10.0.5.7")).unwrap(), ip("10.0.5.7"));
+        assert_eq!(
+            pick_with("interface: eth1", Some("10.0.5.7")).unwrap(),
+            ip("192.168.1.7")
+        );
+        assert_eq!(
+            pick_with(
+                "allow: [10.0.0.0/8, 192.168.0.0/16]\ndeny: [10.0.5.0/24]",
+                None
+            )
+            .unwrap(),
+            ip("192.168.1.7")
+        );
+        assert_eq!(pick_with("prefer: ipv6", None).unwrap(), ip("fd00::7"));
+        assert_eq!(
+            pick_with("interface: lo\nprefer: ipv6", None).unwrap(),
+            ip("::1")
+        );
+        // IPv6-only
+        assert_eq!(pick_with("allow: [fd00::/8]", None).unwrap(), ip("fd00::7"));
+
+        let err = pick_with("interface: wlan0", None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to determine local IP address: no address of interface wlan0 matches uri.network"
+        );
+    }
+
+    #[test]
+    fn test_host_port() {
+        assert_eq!(host_port("10.0.0.7", 8080), "10.0.0.7:8080");
+        assert_eq!(host_port("fd00::7", 8080), "[fd00::7]:8080");
+        assert_eq!(host_port("pod.internal", 8080), "pod.internal:8080");
+    }
+}
@@ -0,0 +1,316 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::config::RegisterConfig;
+use crate::error::{Error, Result};
+use crate::model::{MetaDataRegister, UriRegister};
+use crate::registrar::{context_node, metadata_path, register_urls, Registrar, Tries};
+use crate::report::Attempt;
+use crate::rt;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+pub const CONSUL_KV_SUFFIX: &str = "/v1/kv";
+pub const CONSUL_SERVICE_REGISTER_SUFFIX: &str = "/v1/agent/service/register";
+pub const CONSUL_SERVICE_DEREGISTER_SUFFIX: &str = "/v1/agent/service/deregister";
+pub const CONSUL_CHECK_PASS_SUFFIX: &str = "/v1/agent/check/pass";
+
+/// Registers to Consul with the layout of the Java
+/// `ConsulClientRegisterRepository`:
+///
+/// * metadata is stored in the KV store under the ShenYu register paths;
+/// * the URI is a service of the agent named after the app, carrying the
+///   `URIRegisterDTO` JSON in its `Meta`, with a TTL check passed in the
+///   background.
+///
+/// Supported `register.props`: `token` (ACL token), `ttl` in seconds
+/// (default 10) and `deregisterCriticalServiceAfter` (default `1m`).
+#[derive(Debug)]
+pub struct ConsulRegistrar {
+    inner: Arc<ConsulInner>,
+    check: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Debug)]
+struct ConsulInner {
+    http_client: reqwest::Client,
+    servers: Vec<String>,
+    token: Option<String>,
+    ttl: u64,
+    deregister_after: String,
+}
+
+impl ConsulRegistrar {
+    pub fn new(config: &RegisterConfig, http_client: reqwest::Client) -> Self {
+        let prop = |key: &str| config.props.get(key).filter(|v| !v.is_empty()).cloned();
+        let servers = register_urls(config);
+        ConsulRegistrar {
+            inner: Arc::new(ConsulInner {
+                http_client,
+                servers,
+                token: prop("token"),
+                ttl: prop("ttl")
+                    .and_then(|v| v.parse().ok())
+                    .filter(|ttl| *ttl > 0)
+                    .unwrap_or(10),
+                deregister_after: prop("deregisterCriticalServiceAfter")
+                    .unwrap_or_else(|| "1m".to_string()),
+            }),
+            check: Mutex::new(None),
+        }
+    }
+
+    fn stop_check(&self) {
+        if let Some(check) = self
+            .check
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            check.abort();
+        }
+    }
+}
+
+impl Drop for ConsulRegistrar {
+    fn drop(&mut self) {
+        self.stop_check();
+    }
+}
+
+/// The id of the agent service registered for `uri`.
+pub fn service_id(uri: &UriRegister) -> String {
+    format!("{}-{}-{}", uri.app_name, uri.host, uri.port)
+}
+
+impl ConsulInner {
+    /// PUTs `body` to `suffix` on each server until one answers with a
+    /// success status and a body `accepted` by the caller.
+    async fn put(
+        &self,
+        suffix: &str,
+        body: Option<String>,
+        accepted: fn(&str) -> bool,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        let mut tries = Tries::new(attempts);
+        for server in &self.servers {
+            let url = format!("{}{}", server, suffix);
+            let started = Instant::now();
+            let mut builder = self.http_client.put(&url);
+            if let Some(body) = &body {
+                builder = builder.body(body.clone());
+            }
+            if let Some(token) = &self.token {
+                builder = builder.header("X-Consul-Token", token);
+            }
+            match builder.send().await {
+                Ok(res) => {
+                    let status = res.status();
+                    let body = res.text().await.unwrap_or_default();
+                    let status = status.as_u16();
+                    if (200..300).contains(&status) && accepted(body.trim()) {
+                        tries.succeeded(&url, Some(status), &body, started);
+                        return Ok(());
+                    }
+                    // every agent shares the same catalog, so another one
+                    // would refuse the same way
+                    tries.failed(&url, Error::Rejected { status, body }, started);
+                    break;
+                }
+                Err(e) => tries.failed(&url, Error::transport(&url, e), started),
+            }
+        }
+        Err(tries.into_error())
+    }
+
+    async fn pass_check(&self, service_id: &str) -> Result<()> {
+        self.put(
+            &format!("{}/service:{}", CONSUL_CHECK_PASS_SUFFIX, service_id),
+            None,
+            |_| true,
+            &mut Vec::new(),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl Registrar for ConsulRegistrar {
+    async fn register_metadata(
+        &self,
+        metadata: &MetaDataRegister,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        // consul keys have no leading slash
+        let key = metadata_path(metadata);
+        let suffix = format!("{}/{}", CONSUL_KV_SUFFIX, key.trim_start_matches('/'));
+        self.inner
+            .put(
+                &suffix,
+                Some(serde_json::to_string(metadata)?),
+                |body| body == "true",
+                attempts,
+            )
+            .await
+    }
+
+    async fn register_uri(&self, uri: &UriRegister, attempts: &mut Vec<Attempt>) -> Result<()> {
+        let id = service_id(uri);
+        let service = serde_json::json!({
+            "ID": id,
+            "Name": uri.app_name,
+            "Address": uri.host,
+            "Port": uri.port,
+            "Meta": {
+                "contextPath": context_node(&uri.context_path, &uri.app_name),
+                "uri": serde_json::to_string(uri)?,
+            },
+            "Check": {
+                "CheckID": format!("service:{id}"),
+                "TTL": format!("{}s", self.inner.ttl),
+                "DeregisterCriticalServiceAfter": self.inner.deregister_after,
+            },
+        });
+        self.inner
+            .put(
+                CONSUL_SERVICE_REGISTER_SUFFIX,
+                Some(service.to_string()),
+                |_| true,
+                attempts,
+            )
+            .await?;
+        // the check starts critical, pass it right away
+        if let Err(e) = self.inner.pass_check(&id).await {
+            warn!("Consul check of {} failed: {}", id, e);
+        }
+
+        self.stop_check();
+        let inner = self.inner.clone();
+        let interval = Duration::from_millis(inner.ttl * 1000 / 3);
+        let check = rt::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = inner.pass_check(&id).await {
+                    warn!("Consul check of {} failed: {}", id, e);
+                }
+            }
+        })?;
+        *self.check.lock().unwrap_or_else(PoisonError::into_inner) = Some(check);
+        Ok(())
+    }
+
+    async fn offline(&self, uri: &UriRegister) -> Result<()> {
+        self.stop_check();
+        let suffix = format!("{}/{}", CONSUL_SERVICE_DEREGISTER_SUFFIX, service_id(uri));
+        self.inner
+            .put(&suffix, None, |_| true, &mut Vec::new())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ShenyuClient;
+    use crate::model::UriInfo;
+    use crate::test_support::{config, FakeServer};
+    use axum::http::StatusCode;
+    use serde_json::Value;
+
+    fn consul() -> FakeServer {
+        FakeServer::start(|req| {
+            if req.path().starts_with(CONSUL_KV_SUFFIX) {
+                (StatusCode::OK, "true".to_string())
+            } else {
+                (StatusCode::OK, String::new())
+            }
+        })
+    }
+
+    fn client(consul: &FakeServer) -> ShenyuClient {
+        let mut config = config(&consul.base_url);
+        config.register.register_type = "consul".to_string();
+        config.register.props = [
+            ("token".to_string(), "acl-token".to_string()),
+            ("ttl".to_string(), "1".to_string()),
+        ]
+        .into();
+        let uri_infos = vec![UriInfo::new("/health", "get")];
+        ShenyuClient::builder()
+            .config(config)
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap()
+    }
+
+    fn check_passes(consul: &FakeServer) -> usize {
+        consul
+            .requests()
+            .iter()
+            .filter(|r| r.path().starts_with(CONSUL_CHECK_PASS_SUFFIX))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_register() {
+        let server = consul();
+        let client = client(&server);
+        client.register_async().await.unwrap();
+
+        let kv = server.requests_to("/v1/kv/shenyu/register/metadata/http/xxx/xxx..xxx.health");
+        assert_eq!(kv.len(), 1);
+        assert_eq!(kv[0].method, reqwest::Method::PUT);
+        assert_eq!(kv[0].headers["X-Consul-Token"], "acl-token");
+        assert_eq!(kv[0].json()["path"], "/xxx/health");
+
+        let services = server.requests_to(CONSUL_SERVICE_REGISTER_SUFFIX);
+        assert_eq!(services.len(), 1);
+        let service = services[0].json();
+        assert_eq!(service["Name"], "app1");
+        assert_eq!(service["Port"], 9527);
+        assert_eq!(service["Meta"]["contextPath"], "xxx");
+        let uri: Value = serde_json::from_str(service["Meta"]["uri"].as_str().unwrap()).unwrap();
+        assert_eq!(uri["appName"], "app1");
+        assert_eq!(service["Check"]["TTL"], "1s");
+        let id = service["ID"].as_str().unwrap().to_string();
+        assert!(id.starts_with("app1-") && id.ends_with("-9527"));
+
+        // passed right away, then refreshed in the background
+        assert!(check_passes(&server) >= 1);
+        for _ in 0..100 {
+            if check_passes(&server) >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(check_passes(&server) >= 2);
+
+        client.offline_register_async().await.unwrap();
+        let deregister = format!("{}/{}", CONSUL_SERVICE_DEREGISTER_SUFFIX, id);
+        assert_eq!(server.requests_to(&deregister).len(), 1);
+
+        let passes = check_passes(&server);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(check_passes(&server), passes);
+    }
+}
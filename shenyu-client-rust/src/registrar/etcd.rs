@@ -0,0 +1,597 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::config::RegisterConfig;
+use crate::error::{Error, Result};
+use crate::model::{MetaDataRegister, UriRegister};
+use crate::registrar::{metadata_path, register_urls, uri_path, Registrar, Tries};
+use crate::report::Attempt;
+use crate::rt;
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+pub const ETCD_AUTH_SUFFIX: &str = "/v3/auth/authenticate";
+pub const ETCD_LEASE_GRANT_SUFFIX: &str = "/v3/lease/grant";
+pub const ETCD_LEASE_KEEPALIVE_SUFFIX: &str = "/v3/lease/keepalive";
+pub const ETCD_LEASE_REVOKE_SUFFIX: &str = "/v3/lease/revoke";
+pub const ETCD_PUT_SUFFIX: &str = "/v3/kv/put";
+
+/// The gRPC status code of a refused auth token.
+const ETCD_UNAUTHENTICATED: i64 = 16;
+
+/// Registers to etcd through its v3 JSON gateway. Metadata and URI keys live
+/// under the ShenYu register paths and are bound to a lease kept alive in the
+/// background, so they expire shortly after the process dies.
+///
+/// Supported `register.props`: `etcdTTL` in seconds (default 5), `etcdTimeout`
+/// in milliseconds, and `username`/`password` when etcd auth is enabled, in
+/// which case a token refused by etcd is replaced by a new one.
+#[derive(Debug)]
+pub struct EtcdRegistrar {
+    inner: Arc<EtcdInner>,
+    keepalive: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Debug)]
+struct EtcdInner {
+    http_client: reqwest::Client,
+    servers: Vec<String>,
+    ttl: u64,
+    timeout: Option<Duration>,
+    username: Option<String>,
+    password: Option<String>,
+    token: tokio::sync::Mutex<Option<String>>,
+    lease: tokio::sync::Mutex<Option<String>>,
+    /// Every key put so far, put again when the lease has to be renewed.
+    keys: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl EtcdRegistrar {
+    pub fn new(config: &RegisterConfig, http_client: reqwest::Client) -> Self {
+        let prop = |key: &str| config.props.get(key).filter(|v| !v.is_empty()).cloned();
+        let servers = register_urls(config);
+        EtcdRegistrar {
+            inner: Arc::new(EtcdInner {
+                http_client,
+                servers,
+                ttl: prop("etcdTTL")
+                    .and_then(|v| v.parse().ok())
+                    .filter(|ttl| *ttl > 0)
+                    .unwrap_or(5),
+                timeout: prop("etcdTimeout")
+                    .and_then(|v| v.parse().ok())
+                    .map(Duration::from_millis),
+                username: prop("username"),
+                password: prop("password"),
+                token: tokio::sync::Mutex::new(None),
+                lease: tokio::sync::Mutex::new(None),
+                keys: Mutex::new(HashMap::new()),
+            }),
+            keepalive: Mutex::new(None),
+        }
+    }
+
+    fn stop_keepalive(&self) {
+        if let Some(keepalive) = self
+            .keepalive
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            keepalive.abort();
+        }
+    }
+
+    /// Starts the keepalive task unless it is already running.
+    fn ensure_keepalive(&self) -> Result<()> {
+        let mut keepalive = self
+            .keepalive
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if keepalive.is_some() {
+            return Ok(());
+        }
+        let inner = self.inner.clone();
+        let interval = Duration::from_millis(inner.ttl * 1000 / 3);
+        *keepalive = Some(rt::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = inner.keepalive().await {
+                    warn!("etcd lease keepalive failed: {}", e);
+                }
+            }
+        })?);
+        Ok(())
+    }
+
+    async fn put(&self, key: String, value: Vec<u8>, attempts: &mut Vec<Attempt>) -> Result<()> {
+        let lease = self.inner.lease().await?;
+        self.inner.put(&key, &value, &lease, attempts).await?;
+        self.inner
+            .keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, value);
+        self.ensure_keepalive()
+    }
+}
+
+impl Drop for EtcdRegistrar {
+    fn drop(&mut self) {
+        self.stop_keepalive();
+    }
+}
+
+impl EtcdInner {
+    /// Like [`Self::request_any`] with the current token; when etcd refuses
+    /// it, authenticates again and sends the call once more.
+    async fn call(&self, suffix: &str, body: &Value, attempts: &mut Vec<Attempt>) -> Result<Value> {
+        let token = self.token().await?;
+        match self
+            .request_any(suffix, body, token.as_deref(), attempts)
+            .await
+        {
+            Err(Error::Auth(e)) if token.is_some() => {
+                self.forget_token(token.as_deref()).await;
+                let token = self.token().await?;
+                info!("{}, sent again with a new token", e);
+                self.request_any(suffix, body, token.as_deref(), attempts)
+                    .await
+            }
+            res => res,
+        }
+    }
+
+    /// Posts `body` to `suffix` on each server until one answers with a
+    /// success status.
+    async fn request_any(
+        &self,
+        suffix: &str,
+        body: &Value,
+        token: Option<&str>,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<Value> {
+        let mut tries = Tries::new(attempts);
+        for server in &self.servers {
+            let url = format!("{}{}", server, suffix);
+            let started = Instant::now();
+            let mut builder = self.http_client.post(&url).json(body);
+            if let Some(timeout) = self.timeout {
+                builder = builder.timeout(timeout);
+            }
+            if let Some(token) = token {
+                builder = builder.header("Authorization", token);
+            }
+            match builder.send().await {
+                Ok(res) => {
+                    let status = res.status().as_u16();
+                    let body = match res.text().await {
+                        Ok(body) => body,
+                        Err(e) => {
+                            tries.failed(&url, Error::transport(&url, e), started);
+                            continue;
+                        }
+                    };
+                    if is_token_error(status, &body) {
+                        let e = Error::Auth(format!(
+                            "{url}: auth token refused with status {status}: {body}"
+                        ));
+                        tries.failed(&url, e, started);
+                        continue;
+                    }
+                    if !(200..300).contains(&status) {
+                        tries.failed(&url, Error::Rejected { status, body }, started);
+                        continue;
+                    }
+                    return match serde_json::from_str(&body) {
+                        Ok(value) => {
+                            tries.succeeded(&url, Some(status), &body, started);
+                            Ok(value)
+                        }
+                        Err(e) => {
+                            let e = Error::InvalidData(format!("{url}: {e}"));
+                            tries.failed(&url, e, started);
+                            Err(tries.into_error())
+                        }
+                    };
+                }
+                Err(e) => tries.failed(&url, Error::transport(&url, e), started),
+            }
+        }
+        Err(tries.into_error())
+    }
+
+    async fn token(&self) -> Result<Option<String>> {
+        let (Some(username), Some(password)) = (&self.username, &self.password) else {
+            return Ok(None);
+        };
+        let mut token = self.token.lock().await;
+        if let Some(token) = token.as_ref() {
+            return Ok(Some(token.clone()));
+        }
+        let body = serde_json::json!({ "name": username, "password": password });
+        let mut attempts = Vec::new();
+        let mut tries = Tries::new(&mut attempts);
+        for server in &self.servers {
+            let url = format!("{}{}", server, ETCD_AUTH_SUFFIX);
+            let started = Instant::now();
+            let res = match self.http_client.post(&url).json(&body).send().await {
+                Ok(res) => res.json::<Value>().await.ok(),
+                Err(e) => {
+                    tries.failed(&url, Error::transport(&url, e), started);
+                    continue;
+                }
+            };
+            match res.as_ref().and_then(|v| v["token"].as_str()) {
+                Some(value) => {
+                    *token = Some(value.to_string());
+                    return Ok(token.clone());
+                }
+                None => tries.failed(
+                    &url,
+                    Error::Auth(format!("{url}: no token granted")),
+                    started,
+                ),
+            }
+        }
+        Err(tries.into_error())
+    }
+
+    /// Drops the cached token if it is still `refused`, so that the next
+    /// [`Self::token`] authenticates again.
+    async fn forget_token(&self, refused: Option<&str>) {
+        let mut token = self.token.lock().await;
+        if token.as_deref() == refused {
+            *token = None;
+        }
+    }
+
+    /// Returns the current lease, granting one if there is none yet.
+    async fn lease(&self) -> Result<String> {
+        let mut lease = self.lease.lock().await;
+        if let Some(id) = lease.as_ref() {
+            return Ok(id.clone());
+        }
+        let id = self.grant().await?;
+        *lease = Some(id.clone());
+        Ok(id)
+    }
+
+    async fn grant(&self) -> Result<String> {
+        let res = self
+            .call(
+                ETCD_LEASE_GRANT_SUFFIX,
+                &serde_json::json!({ "TTL": self.ttl }),
+                &mut Vec::new(),
+            )
+            .await?;
+        json_int(&res["ID"]).ok_or_else(|| Error::InvalidData(format!("no lease granted: {res}")))
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        value: &[u8],
+        lease: &str,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        self.call(
+            ETCD_PUT_SUFFIX,
+            &serde_json::json!({
+                "key": BASE64.encode(key),
+                "value": BASE64.encode(value),
+                "lease": lease,
+            }),
+            attempts,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Refreshes the lease; if it has expired meanwhile, grants a new one and
+    /// puts every key again.
+    async fn keepalive(&self) -> Result<()> {
+        let mut lease = self.lease.lock().await;
+        if let Some(id) = lease.as_ref() {
+            let res = self
+                .call(
+                    ETCD_LEASE_KEEPALIVE_SUFFIX,
+                    &serde_json::json!({ "ID": id }),
+                    &mut Vec::new(),
+                )
+                .await?;
+            let result = res.get("result").unwrap_or(&res);
+            if json_int(&result["TTL"]).is_some_and(|ttl| ttl != "0") {
+                return Ok(());
+            }
+            info!("etcd lease {} expired, granting a new one", id);
+        }
+        let id = self.grant().await?;
+        *lease = Some(id.clone());
+        let keys = self
+            .keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for (key, value) in &keys {
+            self.put(key, value, &id, &mut Vec::new()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether etcd refused the call for a missing, invalid or expired token,
+/// which its gateway reports as gRPC code 16 (`Unauthenticated`).
+fn is_token_error(status: u16, body: &str) -> bool {
+    status == 401
+        || serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|res| res.get("code").and_then(Value::as_i64))
+            .is_some_and(|code| code == ETCD_UNAUTHENTICATED)
+}
+
+/// int64 fields are strings in etcd's JSON gateway, but accept numbers too.
+fn json_int(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl Registrar for EtcdRegistrar {
+    async fn login(&self) -> Result<()> {
+        self.inner.token().await.map(|_| ())
+    }
+
+    async fn register_metadata(
+        &self,
+        metadata: &MetaDataRegister,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        self.put(
+            metadata_path(metadata),
+            serde_json::to_vec(metadata)?,
+            attempts,
+        )
+        .await
+    }
+
+    async fn register_uri(&self, uri: &UriRegister, attempts: &mut Vec<Attempt>) -> Result<()> {
+        self.put(uri_path(uri), serde_json::to_vec(uri)?, attempts)
+            .await
+    }
+
+    /// Revokes the lease, which deletes every key registered by this client.
+    async fn offline(&self, _uri: &UriRegister) -> Result<()> {
+        self.stop_keepalive();
+        self.inner
+            .keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        let Some(id) = self.inner.lease.lock().await.take() else {
+            return Ok(());
+        };
+        self.inner
+            .call(
+                ETCD_LEASE_REVOKE_SUFFIX,
+                &serde_json::json!({ "ID": id }),
+                &mut Vec::new(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ShenyuClient;
+    use crate::model::UriInfo;
+    use crate::test_support::{config, FakeServer};
+    use axum::http::StatusCode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fake etcd gateway whose first `expired_keepalives` keepalives report
+    /// the lease as expired.
+    fn etcd(expired_keepalives: usize) -> FakeServer {
+        let leases = AtomicUsize::new(0);
+        let keepalives = AtomicUsize::new(0);
+        FakeServer::start(move |req| match req.path() {
+            ETCD_LEASE_GRANT_SUFFIX => {
+                let id = 100 + leases.fetch_add(1, Ordering::SeqCst);
+                (StatusCode::OK, format!(r#"{{"ID":"{id}","TTL":"1"}}"#))
+            }
+            ETCD_LEASE_KEEPALIVE_SUFFIX => {
+                let id = req.json()["ID"].as_str().unwrap_or_default().to_string();
+                if keepalives.fetch_add(1, Ordering::SeqCst) < expired_keepalives {
+                    (StatusCode::OK, format!(r#"{{"result":{{"ID":"{id}"}}}}"#))
+                } else {
+                    (
+                        StatusCode::OK,
+                        format!(r#"{{"result":{{"ID":"{id}","TTL":"1"}}}}"#),
+                    )
+                }
+            }
+            _ => (StatusCode::OK, "{}".to_string()),
+        })
+    }
+
+    fn client(etcd: &FakeServer) -> ShenyuClient {
+        let mut config = config(&etcd.base_url);
+        config.register.register_type = "etcd".to_string();
+        config.register.props = [("etcdTTL".to_string(), "1".to_string())].into();
+        let uri_infos = vec![UriInfo::new("/health", "get")];
+        ShenyuClient::builder()
+            .config(config)
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap()
+    }
+
+    fn puts(etcd: &FakeServer) -> Vec<(String, String)> {
+        etcd.requests_to(ETCD_PUT_SUFFIX)
+            .iter()
+            .map(|r| {
+                let body = r.json();
+                let key = BASE64.decode(body["key"].as_str().unwrap()).unwrap();
+                let lease = body["lease"].as_str().unwrap().to_string();
+                (String::from_utf8(key).unwrap(), lease)
+            })
+            .collect()
+    }
+
+    async fn wait_for(mut condition: impl FnMut() -> bool) {
+        for _ in 0..100 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    #[tokio::test]
+    async fn test_register_with_lease() {
+        let server = etcd(0);
+        let client = client(&server);
+        client.register_async().await.unwrap();
+
+        assert_eq!(server.requests_to(ETCD_LEASE_GRANT_SUFFIX).len(), 1);
+        let puts = puts(&server);
+        assert_eq!(puts.len(), 2);
+        assert_eq!(
+            puts[0],
+            (
+                "/shenyu/register/metadata/http/xxx/xxx..xxx.health".to_string(),
+                "100".to_string()
+            )
+        );
+        assert!(puts[1].0.starts_with("/shenyu/register/uri/http/xxx/"));
+        assert_eq!(puts[1].1, "100");
+
+        wait_for(|| !server.requests_to(ETCD_LEASE_KEEPALIVE_SUFFIX).is_empty()).await;
+
+        client.offline_register_async().await.unwrap();
+        let revoke = server.requests_to(ETCD_LEASE_REVOKE_SUFFIX);
+        assert_eq!(revoke.len(), 1);
+        assert_eq!(revoke[0].json()["ID"], "100");
+
+        // the keepalive task is stopped along with the lease
+        let keepalives = server.requests_to(ETCD_LEASE_KEEPALIVE_SUFFIX).len();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(
+            server.requests_to(ETCD_LEASE_KEEPALIVE_SUFFIX).len(),
+            keepalives
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_renewed() {
+        let tokens = AtomicUsize::new(0);
+        let calls = AtomicUsize::new(0);
+        // the first token is refused once it has been used for two calls
+        let server = FakeServer::start(move |req| {
+            if req.path() == ETCD_AUTH_SUFFIX {
+                let n = tokens.fetch_add(1, Ordering::SeqCst) + 1;
+                return (StatusCode::OK, format!(r#"{{"token":"t{n}"}}"#));
+            }
+            let token = req.headers["Authorization"].to_str().unwrap().to_string();
+            if token == "t1" && calls.fetch_add(1, Ordering::SeqCst) >= 2 {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    r#"{"error":"etcdserver: invalid auth token","code":16}"#.to_string(),
+                );
+            }
+            match req.path() {
+                ETCD_LEASE_GRANT_SUFFIX => {
+                    (StatusCode::OK, r#"{"ID":"100","TTL":"5"}"#.to_string())
+                }
+                _ => (StatusCode::OK, "{}".to_string()),
+            }
+        });
+        let mut config = config(&server.base_url);
+        config.register.register_type = "etcd".to_string();
+        config.register.props = [
+            ("username".to_string(), "root".to_string()),
+            ("password".to_string(), "secret".to_string()),
+        ]
+        .into();
+        let uri_infos = vec![UriInfo::new("/health", "get")];
+        let client = ShenyuClient::builder()
+            .config(config)
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap();
+        client.register_async().await.unwrap();
+
+        assert_eq!(server.requests_to(ETCD_AUTH_SUFFIX).len(), 2);
+        let tokens: Vec<_> = server
+            .requests_to(ETCD_PUT_SUFFIX)
+            .iter()
+            .map(|r| r.headers["Authorization"].to_str().unwrap().to_string())
+            .collect();
+        assert_eq!(tokens, ["t1", "t1", "t2"]);
+        assert!(puts(&server)[2]
+            .0
+            .starts_with("/shenyu/register/uri/http/xxx/"));
+        client.offline_register_async().await.unwrap();
+    }
+
+    #[test]
+    fn test_is_token_error() {
+        assert!(is_token_error(401, ""));
+        assert!(is_token_error(
+            400,
+            r#"{"error":"etcdserver: invalid auth token","code":16}"#
+        ));
+        assert!(!is_token_error(
+            400,
+            r#"{"error":"lease not found","code":5}"#
+        ));
+        assert!(!is_token_error(200, "{}"));
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_is_renewed() {
+        let server = etcd(1);
+        let client = client(&server);
+        client.register_async().await.unwrap();
+
+        wait_for(|| puts(&server).len() == 4).await;
+        let puts = puts(&server);
+        assert!(puts[2..].iter().all(|(_, lease)| lease == "101"));
+        client.offline_register_async().await.unwrap();
+        assert_eq!(
+            server.requests_to(ETCD_LEASE_REVOKE_SUFFIX)[0].json()["ID"],
+            "101"
+        );
+    }
+}
@@ -0,0 +1,320 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::auth::{self, AuthStrategy};
+use crate::config::RegisterConfig;
+use crate::error::{Error, Result};
+use crate::model::{DiscoveryConfigRegister, MetaDataRegister, UriRegister};
+use crate::registrar::{register_urls, Registrar, Tries};
+use crate::report::Attempt;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+pub const REGISTER_META_DATA_SUFFIX: &str = "/shenyu-client/register-metadata";
+pub const REGISTER_URI_SUFFIX: &str = "/shenyu-client/register-uri";
+pub const REGISTER_DISCOVERY_CONFIG_SUFFIX: &str = "/shenyu-client/register-discoveryConfig";
+pub const REGISTER_OFFLINE_SUFFIX: &str = "/shenyu-client/offline";
+pub const PLATFORM_LOGIN_SUFFIX: &str = "/platform/login";
+
+/// `ShenyuAdminResult` codes of a missing, invalid or expired access token.
+const TOKEN_ERROR_CODES: [i64; 2] = [401, 600];
+
+/// Registers to the ShenYu admin through its `/shenyu-client/*` HTTP endpoints.
+///
+/// Every call is tried against each server of `register.servers` in order,
+/// until one of them accepts it. Requests are authenticated as selected by
+/// `register.props.authType`, see [`auth::from_config`]; when the admin
+/// refuses the credentials, they are renewed and the call is sent once more.
+#[derive(Debug)]
+pub struct HttpRegistrar {
+    http_client: reqwest::Client,
+    headers: Arc<DashMap<String, String>>,
+    auth: Box<dyn AuthStrategy>,
+    register_meta_data_path_list: Vec<String>,
+    register_uri_list: Vec<String>,
+    register_discover_config_servers: Vec<String>,
+    register_offline_servers: Vec<String>,
+}
+
+impl HttpRegistrar {
+    pub fn new(
+        config: &RegisterConfig,
+        http_client: reqwest::Client,
+        headers: Arc<DashMap<String, String>>,
+    ) -> Result<Self> {
+        let gateway_base_urls = register_urls(config);
+        let with_suffix = |suffix: &str| -> Vec<String> {
+            gateway_base_urls
+                .iter()
+                .map(|url| format!("{}{}", url, suffix))
+                .collect()
+        };
+        Ok(HttpRegistrar {
+            auth: auth::from_config(config, http_client.clone(), headers.clone())?,
+            http_client,
+            headers,
+            register_meta_data_path_list: with_suffix(REGISTER_META_DATA_SUFFIX),
+            register_uri_list: with_suffix(REGISTER_URI_SUFFIX),
+            register_discover_config_servers: with_suffix(REGISTER_DISCOVERY_CONFIG_SUFFIX),
+            register_offline_servers: with_suffix(REGISTER_OFFLINE_SUFFIX),
+        })
+    }
+
+    /// Authenticates with `auth` instead of the strategy selected by
+    /// `register.props.authType`.
+    pub fn with_auth(mut self, auth: Box<dyn AuthStrategy>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Posts `json_data` to `url`, returning the status and body it was
+    /// accepted with.
+    async fn request<T: Serialize + Sync>(
+        &self,
+        url: &str,
+        json_data: &T,
+    ) -> Result<(u16, String)> {
+        let body = serde_json::to_vec(json_data)?;
+        let mut builder = self.http_client.post(url);
+        if !self.headers.contains_key(CONTENT_TYPE.as_str()) {
+            builder = builder.header(CONTENT_TYPE, "application/json");
+        }
+        // 遍历header， 添加到builder中
+        for r in self.headers.iter() {
+            builder = builder.header(r.key(), r.value());
+        }
+        let res = self
+            .auth
+            .sign(builder, &body)?
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| Error::transport(url, e))?;
+        let status_code = res.status();
+        let msg = res.text().await.map_err(|e| Error::transport(url, e))?;
+
+        if msg == "success" {
+            Ok((status_code.as_u16(), msg))
+        } else if is_token_error(status_code, &msg) {
+            Err(Error::Auth(format!(
+                "{url}: access token refused with status {status_code}: {msg}"
+            )))
+        } else {
+            Err(Error::Rejected {
+                status: status_code.as_u16(),
+                body: msg,
+            })
+        }
+    }
+
+    /// Posts `json_data` to each of `urls` until one accepts it.
+    async fn request_any<T: Serialize + Sync>(
+        &self,
+        urls: &[String],
+        json_data: &T,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        let mut tries = Tries::new(attempts);
+        for url in urls {
+            let started = Instant::now();
+            match self.request(url, json_data).await {
+                Ok((status, body)) => {
+                    tries.succeeded(url, Some(status), &body, started);
+                    return Ok(());
+                }
+                Err(e) => tries.failed(url, e, started),
+            }
+        }
+        Err(tries.into_error())
+    }
+
+    /// Like [`Self::request_any`], renewing the credentials first when they
+    /// are about to expire, and once more if the admin refuses them anyway.
+    async fn call<T: Serialize + Sync>(
+        &self,
+        urls: &[String],
+        json_data: &T,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        if self.auth.expiring() {
+            info!("Credentials about to expire, renewing them");
+            if let Err(e) = self.auth.renew().await {
+                warn!("Renewing the credentials failed: {}", e);
+            }
+        }
+        match self.request_any(urls, json_data, attempts).await {
+            Err(Error::Auth(e)) => {
+                if !self.auth.renew().await? {
+                    return Err(Error::Auth(e));
+                }
+                info!("{}, sent again with renewed credentials", e);
+                self.request_any(urls, json_data, attempts).await
+            }
+            res => res,
+        }
+    }
+}
+
+fn is_token_error(status: StatusCode, body: &str) -> bool {
+    status == StatusCode::UNAUTHORIZED
+        || serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|res| res.get("code").and_then(Value::as_i64))
+            .is_some_and(|code| TOKEN_ERROR_CODES.contains(&code))
+}
+
+#[async_trait]
+impl Registrar for HttpRegistrar {
+    async fn login(&self) -> Result<()> {
+        self.auth.login().await
+    }
+
+    async fn register_metadata(
+        &self,
+        metadata: &MetaDataRegister,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        self.call(&self.register_meta_data_path_list, metadata, attempts)
+            .await
+    }
+
+    async fn register_uri(&self, uri: &UriRegister, attempts: &mut Vec<Attempt>) -> Result<()> {
+        self.call(&self.register_uri_list, uri, attempts).await
+    }
+
+    async fn register_discovery_config(
+        &self,
+        config: &DiscoveryConfigRegister,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        self.call(&self.register_discover_config_servers, config, attempts)
+            .await
+    }
+
+    async fn offline(&self, uri: &UriRegister) -> Result<()> {
+        self.call(&self.register_offline_servers, uri, &mut Vec::new())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ShenyuClient;
+    use crate::model::UriInfo;
+    use crate::test_support::{config, FakeServer};
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A JWT past its expiry, signed `signature`.
+    fn expired_jwt(signature: &str) -> String {
+        let claims = URL_SAFE_NO_PAD.encode(r#"{"userName":"admin","exp":1700000000}"#);
+        format!("eyJhbGciOiJIUzI1NiJ9.{claims}.{signature}")
+    }
+
+    /// An admin handing out `token(n)` at the n-th login, and accepting only
+    /// the latest token.
+    fn admin(token: fn(usize) -> String) -> FakeServer {
+        let logins = AtomicUsize::new(0);
+        FakeServer::start(move |req| {
+            if req.path() == PLATFORM_LOGIN_SUFFIX {
+                let n = logins.fetch_add(1, Ordering::SeqCst) + 1;
+                let body = format!(r#"{{"code":200,"data":{{"token":"{}"}}}}"#, token(n));
+                return (StatusCode::OK, body);
+            }
+            let latest = token(logins.load(Ordering::SeqCst));
+            match req.headers.get("X-Access-Token") {
+                Some(sent) if *sent == latest.as_str() => (StatusCode::OK, "success".to_string()),
+                _ => (
+                    StatusCode::OK,
+                    r#"{"code":600,"message":"token is expired"}"#.to_string(),
+                ),
+            }
+        })
+    }
+
+    fn client(admin: &FakeServer, props: &[(&str, &str)]) -> ShenyuClient {
+        let mut config = config(&admin.base_url);
+        for (key, value) in props {
+            config
+                .register
+                .props
+                .insert(key.to_string(), value.to_string());
+        }
+        config.retry.max_attempts = 1;
+        let uri_infos = vec![UriInfo::new("/health", "get")];
+        ShenyuClient::builder()
+            .config(config)
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_is_token_error() {
+        assert!(is_token_error(StatusCode::UNAUTHORIZED, ""));
+        assert!(is_token_error(
+            StatusCode::OK,
+            r#"{"code":600,"message":"token is expired"}"#
+        ));
+        assert!(!is_token_error(StatusCode::OK, "success"));
+        assert!(!is_token_error(StatusCode::BAD_REQUEST, r#"{"code":500}"#));
+    }
+
+    #[tokio::test]
+    async fn test_login_again_when_token_refused() {
+        let admin = admin(|n| format!("token-{n}"));
+        let client = client(&admin, &[]);
+        client.register_async().await.unwrap();
+        // simulates the admin restarting and forgetting the token
+        client
+            .headers
+            .insert("X-Access-Token".to_string(), "token-0".to_string());
+        client.offline_register_async().await.unwrap();
+
+        assert_eq!(admin.requests_to(PLATFORM_LOGIN_SUFFIX).len(), 2);
+        let offline = admin.requests_to(REGISTER_OFFLINE_SUFFIX);
+        assert_eq!(offline.len(), 2);
+        assert_eq!(offline[1].headers["X-Access-Token"], "token-2");
+    }
+
+    #[tokio::test]
+    async fn test_refresh_before_expiry() {
+        let admin = admin(|n| expired_jwt(&format!("sig{n}")));
+        let client = client(&admin, &[("tokenRefreshAhead", "60")]);
+        client.register_async().await.unwrap();
+
+        // the login of register(), then one per registered item
+        assert_eq!(admin.requests_to(PLATFORM_LOGIN_SUFFIX).len(), 4);
+        let uris = admin.requests_to(REGISTER_URI_SUFFIX);
+        assert_eq!(uris.len(), 1);
+        assert!(uris[0].headers["X-Access-Token"]
+            .to_str()
+            .unwrap()
+            .ends_with("sig3"));
+    }
+}
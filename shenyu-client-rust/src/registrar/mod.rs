@@ -0,0 +1,284 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Transports to the register centers supported by ShenYu.
+//!
+//! The transport is selected by `register.register_type` in the config, see
+//! [`from_config`].
+
+use crate::config::{RegisterConfig, ShenYuConfig};
+use crate::error::{Error, Result};
+use crate::model::{DiscoveryConfigRegister, MetaDataRegister, UriRegister};
+use crate::report::Attempt;
+use crate::{network, transport};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+pub mod consul;
+pub mod etcd;
+pub mod http;
+pub mod nacos;
+pub mod zookeeper;
+
+pub use consul::ConsulRegistrar;
+pub use etcd::EtcdRegistrar;
+pub use http::HttpRegistrar;
+pub use nacos::NacosRegistrar;
+pub use zookeeper::ZookeeperRegistrar;
+
+/// The root of ShenYu's register paths in ZooKeeper, etcd and Consul.
+pub const ROOT_PATH: &str = "/shenyu/register";
+
+/// A register center the client publishes its routes and address to.
+///
+/// Every method fails with [`Error::Rejected`] when the register center
+/// answered but refused the registration, and [`Error::Transport`] when it
+/// could not be reached. The registering methods push every request they
+/// send, successful or not, to `attempts`.
+#[async_trait]
+pub trait Registrar: Debug + Send + Sync {
+    /// Prepares the registrar before anything is registered, e.g. logs in.
+    async fn login(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn register_metadata(
+        &self,
+        metadata: &MetaDataRegister,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()>;
+
+    async fn register_uri(&self, uri: &UriRegister, attempts: &mut Vec<Attempt>) -> Result<()>;
+
+    /// Registers a discovery config; only the http register center takes
+    /// them, the others skip it.
+    async fn register_discovery_config(
+        &self,
+        config: &DiscoveryConfigRegister,
+        _attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        info!(
+            "Discovery config {} is only registered through the http register center, skipped",
+            config.name
+        );
+        Ok(())
+    }
+
+    /// Removes this instance from the register center.
+    async fn offline(&self, uri: &UriRegister) -> Result<()>;
+}
+
+/// Creates the registrar matching `register.register_type`.
+///
+/// `headers` are sent with every request of HTTP based registrars.
+pub fn from_config(
+    config: &ShenYuConfig,
+    http_client: reqwest::Client,
+    headers: Arc<DashMap<String, String>>,
+) -> Result<Box<dyn Registrar>> {
+    match config.register.register_type.as_str() {
+        "http" => Ok(Box::new(HttpRegistrar::new(
+            &config.register,
+            http_client,
+            headers,
+        )?)),
+        "consul" => Ok(Box::new(ConsulRegistrar::new(
+            &config.register,
+            http_client,
+        ))),
+        "etcd" => Ok(Box::new(EtcdRegistrar::new(&config.register, http_client))),
+        "nacos" => Ok(Box::new(NacosRegistrar::new(&config.register, http_client))),
+        "zookeeper" => Ok(Box::new(ZookeeperRegistrar::new(&config.register))),
+        other => Err(Error::UnsupportedRegisterType(other.to_string())),
+    }
+}
+
+/// The requests of a call tried against every server, recorded as
+/// [`Attempt`]s.
+pub(crate) struct Tries<'a> {
+    attempts: &'a mut Vec<Attempt>,
+    refused: Option<Error>,
+    unreachable: Option<Error>,
+}
+
+impl<'a> Tries<'a> {
+    pub(crate) fn new(attempts: &'a mut Vec<Attempt>) -> Self {
+        Tries {
+            attempts,
+            refused: None,
+            unreachable: None,
+        }
+    }
+
+    pub(crate) fn succeeded(
+        &mut self,
+        url: &str,
+        status: Option<u16>,
+        body: &str,
+        started: Instant,
+    ) {
+        self.attempts.push(Attempt::succeeded(
+            url,
+            status,
+            Some(body.to_string()),
+            started,
+        ));
+    }
+
+    pub(crate) fn failed(&mut self, url: &str, e: Error, started: Instant) {
+        warn!("Request ({}) failed: {}", url, e);
+        self.attempts.push(Attempt::failed(url, &e, started));
+        match e {
+            Error::Transport { .. } => self.unreachable = Some(e),
+            e => self.refused = Some(e),
+        }
+    }
+
+    /// The last refusal if any server answered, as it tells more than a
+    /// server that couldn't be reached.
+    pub(crate) fn into_error(self) -> Error {
+        self.refused
+            .or(self.unreachable)
+            .unwrap_or_else(|| Error::Config("register.servers is empty".to_string()))
+    }
+}
+
+/// The base URLs of the comma separated `servers`, `http://` unless they
+/// name their scheme, without a trailing `/`.
+pub(crate) fn server_urls(servers: &str) -> Vec<String> {
+    servers
+        .split(',')
+        .map(|s| s.trim().trim_end_matches('/'))
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if s.contains("://") {
+                s.to_string()
+            } else {
+                format!("http://{s}")
+            }
+        })
+        .collect()
+}
+
+/// The base URLs of `register.servers`, see [`server_urls`], with the IP
+/// host replaced by `register.tls.server_name` when set; the HTTP client
+/// resolves that name back to the IP.
+pub(crate) fn register_urls(register: &RegisterConfig) -> Vec<String> {
+    let urls = server_urls(&register.servers);
+    match &register.tls.server_name {
+        Some(name) => urls
+            .into_iter()
+            .map(|url| transport::pin_server_name(&url, name).map_or(url, |(url, _)| url))
+            .collect(),
+        None => urls,
+    }
+}
+
+/// The context path as a node name: without its leading `/`, or the app name
+/// when it is empty.
+pub(crate) fn context_node(context_path: &str, app_name: &str) -> String {
+    match context_path.trim().trim_start_matches('/') {
+        "" => app_name.to_string(),
+        node => node.to_string(),
+    }
+}
+
+/// The path of `metadata` under [`ROOT_PATH`], laid out like the Java client:
+/// `/shenyu/register/metadata/{rpcType}/{contextPath}/{nodeName}`.
+pub fn metadata_path(metadata: &MetaDataRegister) -> String {
+    let node_name = match metadata.rpc_type.as_str() {
+        "http" | "springCloud" => format!(
+            "{}.{}",
+            metadata.context_path,
+            metadata.rule_name.replace('/', ".")
+        ),
+        _ => format!("{}.{}", metadata.service_name, metadata.method_name),
+    };
+    format!(
+        "{}/metadata/{}/{}/{}",
+        ROOT_PATH,
+        metadata.rpc_type,
+        context_node(&metadata.context_path, &metadata.app_name),
+        node_name.trim_start_matches('/')
+    )
+}
+
+/// The path of `uri` under [`ROOT_PATH`], laid out like the Java client:
+/// `/shenyu/register/uri/{rpcType}/{contextPath}/{host}:{port}`, with an
+/// IPv6 host in brackets.
+pub fn uri_path(uri: &UriRegister) -> String {
+    format!(
+        "{}/uri/{}/{}/{}",
+        ROOT_PATH,
+        uri.rpc_type,
+        context_node(&uri.context_path, &uri.app_name),
+        network::host_port(&uri.host, uri.port)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(context_path: &str) -> UriRegister {
+        UriRegister {
+            app_name: "app1".to_string(),
+            context_path: context_path.to_string(),
+            protocol: "http".to_string(),
+            rpc_type: "http".to_string(),
+            host: "10.0.0.1".to_string(),
+            port: 8080,
+            event_type: "REGISTER".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_server_urls() {
+        assert_eq!(
+            server_urls("http://10.0.0.1:9095, 10.0.0.2:8848/,https://etcd:2379,"),
+            [
+                "http://10.0.0.1:9095",
+                "http://10.0.0.2:8848",
+                "https://etcd:2379"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uri_path() {
+        assert_eq!(
+            uri_path(&uri("/order")),
+            "/shenyu/register/uri/http/order/10.0.0.1:8080"
+        );
+        assert_eq!(
+            uri_path(&uri("")),
+            "/shenyu/register/uri/http/app1/10.0.0.1:8080"
+        );
+        let uri = UriRegister {
+            host: "fd00::7".to_string(),
+            ..uri("/order")
+        };
+        assert_eq!(
+            uri_path(&uri),
+            "/shenyu/register/uri/http/order/[fd00::7]:8080"
+        );
+    }
+}
@@ -0,0 +1,565 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::config::RegisterConfig;
+use crate::error::{Error, Result};
+use crate::model::{MetaDataRegister, UriRegister};
+use crate::registrar::{context_node, register_urls, Registrar, Tries, ROOT_PATH};
+use crate::report::Attempt;
+use crate::rt;
+use async_trait::async_trait;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use serde_json::Value;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+pub const NACOS_LOGIN_SUFFIX: &str = "/nacos/v1/auth/login";
+pub const NACOS_CONFIG_SUFFIX: &str = "/nacos/v1/cs/configs";
+pub const NACOS_INSTANCE_SUFFIX: &str = "/nacos/v1/ns/instance";
+pub const NACOS_BEAT_SUFFIX: &str = "/nacos/v1/ns/instance/beat";
+pub const NACOS_GROUP: &str = "DEFAULT_GROUP";
+
+const DEFAULT_BEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Registers to Nacos with the key layout of the Java
+/// `NacosClientRegisterRepository`:
+///
+/// * metadata is published as the config `shenyu.register.service.{rpcType}.{contextPath}`
+///   in `DEFAULT_GROUP`, holding the JSON list of every route;
+/// * the URI is an ephemeral instance of the service
+///   `shenyu.register.service.{rpcType}`, kept alive by a background beat.
+///
+/// Supported `register.props`: `nacosNameSpace`, `username`, `password`,
+/// `accessKey` and `secretKey`.
+#[derive(Debug)]
+pub struct NacosRegistrar {
+    inner: Arc<NacosInner>,
+    /// Published metadata as `(path, json)`, in registration order.
+    metadata_cache: tokio::sync::Mutex<Vec<(String, String)>>,
+    beat: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Debug)]
+struct NacosInner {
+    http_client: reqwest::Client,
+    servers: Vec<String>,
+    namespace: String,
+    username: Option<String>,
+    password: Option<String>,
+    access_key: Option<String>,
+    secret_key: Option<String>,
+    token: tokio::sync::Mutex<Option<(String, Instant)>>,
+}
+
+impl NacosRegistrar {
+    pub fn new(config: &RegisterConfig, http_client: reqwest::Client) -> Self {
+        let prop = |key: &str| config.props.get(key).filter(|v| !v.is_empty()).cloned();
+        let servers = register_urls(config);
+        NacosRegistrar {
+            inner: Arc::new(NacosInner {
+                http_client,
+                servers,
+                namespace: prop("nacosNameSpace").unwrap_or_default(),
+                username: prop("username"),
+                password: prop("password"),
+                access_key: prop("accessKey"),
+                secret_key: prop("secretKey"),
+                token: tokio::sync::Mutex::new(None),
+            }),
+            metadata_cache: tokio::sync::Mutex::new(Vec::new()),
+            beat: Mutex::new(None),
+        }
+    }
+
+    fn stop_beat(&self) {
+        if let Some(beat) = self
+            .beat
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            beat.abort();
+        }
+    }
+}
+
+impl Drop for NacosRegistrar {
+    fn drop(&mut self) {
+        self.stop_beat();
+    }
+}
+
+/// `shenyu.register.service.{rpcType}`, the service URIs are instances of.
+pub fn service_instance_name(rpc_type: &str) -> String {
+    dotted(&format!("{}/service/{}", ROOT_PATH, rpc_type))
+}
+
+/// `shenyu.register.service.{rpcType}.{contextPath}`, the config holding the
+/// metadata of a context path.
+pub fn service_config_name(rpc_type: &str, context_path: &str) -> String {
+    dotted(&format!("{}/service/{}/{}", ROOT_PATH, rpc_type, context_path).replace('*', ""))
+}
+
+fn dotted(path: &str) -> String {
+    path.replace('/', ".")
+        .trim_start_matches('.')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn timestamp_millis() -> String {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+        .to_string()
+}
+
+fn sign(secret_key: &str, data: &str) -> Result<String> {
+    let mut mac = Hmac::<sha1::Sha1>::new_from_slice(secret_key.as_bytes())
+        .map_err(|e| Error::Auth(format!("invalid secretKey: {e}")))?;
+    mac.update(data.as_bytes());
+    Ok(base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+}
+
+impl NacosInner {
+    /// Returns the access token, logging in again once it is about to expire.
+    async fn access_token(&self) -> Result<Option<String>> {
+        let (Some(username), Some(password)) = (&self.username, &self.password) else {
+            return Ok(None);
+        };
+        let mut token = self.token.lock().await;
+        if let Some((token, expires_at)) = token.as_ref() {
+            if Instant::now() < *expires_at {
+                return Ok(Some(token.clone()));
+            }
+        }
+        let mut attempts = Vec::new();
+        let mut tries = Tries::new(&mut attempts);
+        for server in &self.servers {
+            let url = format!("{}{}", server, NACOS_LOGIN_SUFFIX);
+            let started = Instant::now();
+            let res = self
+                .http_client
+                .post(&url)
+                .form(&[("username", username), ("password", password)])
+                .send()
+                .await;
+            let body: Value = match res {
+                Ok(res) if res.status().is_success() => match res.json().await {
+                    Ok(body) => body,
+                    Err(e) => {
+                        tries.failed(&url, Error::Auth(format!("{url}: {e}")), started);
+                        continue;
+                    }
+                },
+                Ok(res) => {
+                    let status = res.status().as_u16();
+                    let body = res.text().await.unwrap_or_default();
+                    tries.failed(
+                        &url,
+                        Error::Auth(format!("{url}: status {status}, {body}")),
+                        started,
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    tries.failed(&url, Error::transport(&url, e), started);
+                    continue;
+                }
+            };
+            if let Some(access_token) = body.get("accessToken").and_then(Value::as_str) {
+                let ttl = body
+                    .get("tokenTtl")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(18_000);
+                // refresh a little before the token actually expires
+                let expires_at = Instant::now() + Duration::from_secs(ttl * 9 / 10);
+                *token = Some((access_token.to_string(), expires_at));
+                return Ok(Some(access_token.to_string()));
+            }
+            tries.failed(
+                &url,
+                Error::Auth(format!("{url}: no accessToken in {body}")),
+                started,
+            );
+        }
+        Err(tries.into_error())
+    }
+
+    /// Sends `params` to `suffix` on each server until one answers with a
+    /// success status, returning its body.
+    ///
+    /// `accepted` tells whether that body means the call succeeded.
+    async fn call(
+        &self,
+        method: Method,
+        suffix: &str,
+        mut params: Vec<(&str, String)>,
+        spas_resource: Option<String>,
+        accepted: fn(&str) -> bool,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<String> {
+        if let Some(token) = self.access_token().await? {
+            params.push(("accessToken", token));
+        }
+        let mut headers = Vec::new();
+        if let (Some(ak), Some(sk)) = (&self.access_key, &self.secret_key) {
+            let timestamp = timestamp_millis();
+            match spas_resource {
+                // config service
+                Some(resource) => {
+                    let signature = sign(sk, &format!("{resource}+{timestamp}"))?;
+                    headers.push(("Spas-AccessKey", ak.clone()));
+                    headers.push(("Spas-Signature", signature));
+                    headers.push(("Timestamp", timestamp));
+                }
+                // naming service
+                None => {
+                    let service_name = params
+                        .iter()
+                        .find(|(k, _)| *k == "serviceName")
+                        .map(|(_, v)| v.clone())
+                        .unwrap_or_default();
+                    let data = format!("{timestamp}@@{service_name}");
+                    params.push(("signature", sign(sk, &data)?));
+                    params.push(("data", data));
+                    params.push(("ak", ak.clone()));
+                }
+            }
+        }
+
+        let mut tries = Tries::new(attempts);
+        for server in &self.servers {
+            let url = format!("{}{}", server, suffix);
+            let started = Instant::now();
+            let mut builder = self.http_client.request(method.clone(), &url);
+            builder = if method == Method::POST {
+                builder.form(&params)
+            } else {
+                builder.query(&params)
+            };
+            for (name, value) in &headers {
+                builder = builder.header(*name, value);
+            }
+            match builder.send().await {
+                Ok(res) => {
+                    let status = res.status();
+                    let body = res.text().await.unwrap_or_default();
+                    let status = status.as_u16();
+                    if (200..300).contains(&status) && accepted(body.trim()) {
+                        tries.succeeded(&url, Some(status), &body, started);
+                        return Ok(body);
+                    }
+                    tries.failed(&url, Error::Rejected { status, body }, started);
+                }
+                Err(e) => tries.failed(&url, Error::transport(&url, e), started),
+            }
+        }
+        Err(tries.into_error())
+    }
+
+    fn instance_params(&self, uri: &UriRegister) -> Vec<(&'static str, String)> {
+        vec![
+            ("serviceName", service_instance_name(&uri.rpc_type)),
+            ("groupName", NACOS_GROUP.to_string()),
+            ("namespaceId", self.namespace.clone()),
+            ("ip", uri.host.clone()),
+            ("port", uri.port.to_string()),
+            ("ephemeral", "true".to_string()),
+        ]
+    }
+
+    async fn beat(&self, uri: &UriRegister, metadata: &Value) -> Option<Duration> {
+        let beat = serde_json::json!({
+            "serviceName": format!("{}@@{}", NACOS_GROUP, service_instance_name(&uri.rpc_type)),
+            "ip": uri.host,
+            "port": uri.port,
+            "cluster": "DEFAULT",
+            "weight": 1.0,
+            "metadata": metadata,
+            "scheduled": false,
+        });
+        let mut params = self.instance_params(uri);
+        params.push(("beat", beat.to_string()));
+        match self
+            .call(
+                Method::PUT,
+                NACOS_BEAT_SUFFIX,
+                params,
+                None,
+                |_| true,
+                &mut Vec::new(),
+            )
+            .await
+        {
+            Ok(body) => serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|v| v.get("clientBeatInterval").and_then(Value::as_u64))
+                .map(Duration::from_millis),
+            Err(e) => {
+                warn!("Nacos beat failed: {}", e);
+                None
+            }
+        }
+    }
+}
+
+fn uri_metadata(uri: &UriRegister) -> Result<Value> {
+    let uri_json = serde_json::to_string(uri)?;
+    Ok(serde_json::json!({
+        "contextPath": context_node(&uri.context_path, &uri.app_name),
+        "uriMetadata": uri_json,
+    }))
+}
+
+#[async_trait]
+impl Registrar for NacosRegistrar {
+    async fn login(&self) -> Result<()> {
+        self.inner.access_token().await.map(|_| ())
+    }
+
+    async fn register_metadata(
+        &self,
+        metadata: &MetaDataRegister,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        let json = serde_json::to_string(metadata)?;
+        let context_path = context_node(&metadata.context_path, &metadata.app_name);
+        let data_id = service_config_name(&metadata.rpc_type, &context_path);
+
+        let mut cache = self.metadata_cache.lock().await;
+        match cache.iter_mut().find(|(path, _)| *path == metadata.path) {
+            Some(entry) => entry.1 = json,
+            None => cache.push((metadata.path.clone(), json)),
+        }
+        let content: Vec<&String> = cache.iter().map(|(_, json)| json).collect();
+        let content = serde_json::to_string(&content)?;
+
+        let resource = if self.inner.namespace.is_empty() {
+            NACOS_GROUP.to_string()
+        } else {
+            format!("{}+{}", self.inner.namespace, NACOS_GROUP)
+        };
+        let params = vec![
+            ("dataId", data_id),
+            ("group", NACOS_GROUP.to_string()),
+            ("tenant", self.inner.namespace.clone()),
+            ("type", "json".to_string()),
+            ("content", content),
+        ];
+        self.inner
+            .call(
+                Method::POST,
+                NACOS_CONFIG_SUFFIX,
+                params,
+                Some(resource),
+                |body| body == "true",
+                attempts,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn register_uri(&self, uri: &UriRegister, attempts: &mut Vec<Attempt>) -> Result<()> {
+        let metadata = uri_metadata(uri)?;
+        let mut params = self.inner.instance_params(uri);
+        params.push(("metadata", metadata.to_string()));
+        params.push(("healthy", "true".to_string()));
+        params.push(("enabled", "true".to_string()));
+        params.push(("weight", "1.0".to_string()));
+        self.inner
+            .call(
+                Method::POST,
+                NACOS_INSTANCE_SUFFIX,
+                params,
+                None,
+                |body| body == "ok",
+                attempts,
+            )
+            .await?;
+
+        self.stop_beat();
+        let inner = self.inner.clone();
+        let uri = uri.clone();
+        let beat = rt::spawn(async move {
+            loop {
+                let interval = inner
+                    .beat(&uri, &metadata)
+                    .await
+                    .unwrap_or(DEFAULT_BEAT_INTERVAL);
+                tokio::time::sleep(interval).await;
+            }
+        })?;
+        *self.beat.lock().unwrap_or_else(PoisonError::into_inner) = Some(beat);
+        Ok(())
+    }
+
+    async fn offline(&self, uri: &UriRegister) -> Result<()> {
+        self.stop_beat();
+        self.inner
+            .call(
+                Method::DELETE,
+                NACOS_INSTANCE_SUFFIX,
+                self.inner.instance_params(uri),
+                None,
+                |_| true,
+                &mut Vec::new(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ShenyuClient;
+    use crate::model::UriInfo;
+    use crate::test_support::{config, FakeServer};
+    use axum::http::StatusCode;
+
+    fn nacos() -> FakeServer {
+        FakeServer::start(|req| match req.path() {
+            NACOS_LOGIN_SUFFIX => (
+                StatusCode::OK,
+                r#"{"accessToken":"nacos-token","tokenTtl":18000,"globalAdmin":true}"#.to_string(),
+            ),
+            NACOS_CONFIG_SUFFIX => (StatusCode::OK, "true".to_string()),
+            NACOS_BEAT_SUFFIX => (StatusCode::OK, r#"{"clientBeatInterval":5000}"#.to_string()),
+            _ => (StatusCode::OK, "ok".to_string()),
+        })
+    }
+
+    fn client(nacos: &FakeServer, props: &[(&str, &str)]) -> ShenyuClient {
+        let mut config = config(&nacos.base_url);
+        config.register.register_type = "nacos".to_string();
+        config.register.props = props
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let uri_infos = ["/health", "/users"]
+            .iter()
+            .map(|path| UriInfo::new(path, "get"))
+            .collect::<Vec<_>>();
+        ShenyuClient::builder()
+            .config(config)
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_key_layout() {
+        assert_eq!(
+            service_instance_name("http"),
+            "shenyu.register.service.http"
+        );
+        assert_eq!(
+            service_config_name("http", "xxx"),
+            "shenyu.register.service.http.xxx"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_register() {
+        let server = nacos();
+        let client = client(
+            &server,
+            &[
+                ("nacosNameSpace", "ns"),
+                ("username", "nacos"),
+                ("password", "nacos"),
+            ],
+        );
+        client.register_async().await.unwrap();
+
+        let login = server.requests_to(NACOS_LOGIN_SUFFIX);
+        assert_eq!(login.len(), 1);
+        assert_eq!(login[0].params()["username"], "nacos");
+
+        let configs = server.requests_to(NACOS_CONFIG_SUFFIX);
+        assert_eq!(configs.len(), 2);
+        let params = configs[1].params();
+        assert_eq!(params["dataId"], "shenyu.register.service.http.xxx");
+        assert_eq!(params["group"], NACOS_GROUP);
+        assert_eq!(params["tenant"], "ns");
+        assert_eq!(params["accessToken"], "nacos-token");
+        let content: Vec<String> = serde_json::from_str(&params["content"]).unwrap();
+        assert_eq!(content.len(), 2);
+        assert!(content[1].contains("/xxx/users"));
+
+        let instances = server.requests_to(NACOS_INSTANCE_SUFFIX);
+        assert_eq!(instances.len(), 1);
+        let params = instances[0].params();
+        assert_eq!(params["serviceName"], "shenyu.register.service.http");
+        assert_eq!(params["namespaceId"], "ns");
+        assert_eq!(params["port"], "9527");
+        assert_eq!(params["ephemeral"], "true");
+        let metadata: Value = serde_json::from_str(&params["metadata"]).unwrap();
+        assert_eq!(metadata["contextPath"], "xxx");
+        assert!(metadata["uriMetadata"].as_str().unwrap().contains("app1"));
+
+        for _ in 0..50 {
+            if !server.requests_to(NACOS_BEAT_SUFFIX).is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!server.requests_to(NACOS_BEAT_SUFFIX).is_empty());
+
+        client.offline_register_async().await.unwrap();
+        let deregister = server
+            .requests()
+            .into_iter()
+            .filter(|r| r.is(Method::DELETE, NACOS_INSTANCE_SUFFIX))
+            .count();
+        assert_eq!(deregister, 1);
+    }
+
+    #[tokio::test]
+    async fn test_access_key_signature() {
+        let server = nacos();
+        let client = client(&server, &[("accessKey", "ak"), ("secretKey", "sk")]);
+        client.register_uri_async().await.unwrap();
+        client.register_all_metadata_async(true).await.unwrap();
+
+        assert!(server.requests_to(NACOS_LOGIN_SUFFIX).is_empty());
+        let instance = &server.requests_to(NACOS_INSTANCE_SUFFIX)[0].params();
+        assert_eq!(instance["ak"], "ak");
+        assert_eq!(
+            instance["signature"],
+            sign("sk", &instance["data"]).unwrap()
+        );
+        assert!(instance["data"].ends_with("@@shenyu.register.service.http"));
+
+        let config = &server.requests_to(NACOS_CONFIG_SUFFIX)[0];
+        assert_eq!(config.headers["Spas-AccessKey"], "ak");
+        let timestamp = config.headers["Timestamp"].to_str().unwrap();
+        assert_eq!(
+            config.headers["Spas-Signature"],
+            sign("sk", &format!("{NACOS_GROUP}+{timestamp}")).unwrap()
+        );
+        client.offline_register_async().await.unwrap();
+    }
+}
@@ -0,0 +1,267 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::config::RegisterConfig;
+use crate::error::{Error, Result};
+use crate::model::{MetaDataRegister, UriRegister};
+use crate::registrar::{metadata_path, uri_path, Registrar};
+use crate::report::Attempt;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use zookeeper_client::{Acls, Client, CreateMode};
+
+/// Registers to ZooKeeper by writing ephemeral nodes under the ShenYu
+/// register paths, so they vanish with the session when the process dies.
+///
+/// Supported `register.props`: `sessionTimeout` and `connectionTimeout` in
+/// milliseconds, and `digest` as `user:password`.
+#[derive(Debug)]
+pub struct ZookeeperRegistrar {
+    servers: String,
+    session_timeout: Duration,
+    connection_timeout: Duration,
+    digest: Option<String>,
+    client: tokio::sync::Mutex<Option<Client>>,
+    /// Every node created so far, recreated when the session expires.
+    nodes: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl ZookeeperRegistrar {
+    pub fn new(config: &RegisterConfig) -> Self {
+        let millis = |key: &str, default: u64| {
+            config
+                .props
+                .get(key)
+                .and_then(|v| v.parse().ok())
+                .map_or(Duration::from_millis(default), Duration::from_millis)
+        };
+        ZookeeperRegistrar {
+            servers: config.servers.clone(),
+            session_timeout: millis("sessionTimeout", 30_000),
+            connection_timeout: millis("connectionTimeout", 3_000),
+            digest: config.props.get("digest").cloned(),
+            client: tokio::sync::Mutex::new(None),
+            nodes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the live session, reconnecting and recreating the registered
+    /// nodes if the previous one has terminated.
+    async fn client(&self) -> Result<Client> {
+        let mut guard = self.client.lock().await;
+        if let Some(client) = guard.as_ref() {
+            if !client.state().is_terminated() {
+                return Ok(client.clone());
+            }
+        }
+        let mut connector = Client::connector();
+        connector
+            .session_timeout(self.session_timeout)
+            .connection_timeout(self.connection_timeout);
+        if let Some(digest) = &self.digest {
+            connector.auth("digest".to_string(), digest.as_bytes().to_vec());
+        }
+        let client = connector
+            .connect(&self.servers)
+            .await
+            .map_err(|e| zk_error(&self.servers, e))?;
+        let nodes = self
+            .nodes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for (path, data) in &nodes {
+            create_ephemeral(&client, path, data).await?;
+        }
+        *guard = Some(client.clone());
+        Ok(client)
+    }
+
+    /// Like [`Self::create_or_update`], recording the outcome as an attempt
+    /// on `register.servers`.
+    async fn register(&self, path: &str, data: Vec<u8>, attempts: &mut Vec<Attempt>) -> Result<()> {
+        let started = Instant::now();
+        let res = self.create_or_update(path, data).await;
+        attempts.push(match &res {
+            Ok(()) => Attempt::succeeded(&self.servers, None, None, started),
+            Err(e) => Attempt::failed(&self.servers, e, started),
+        });
+        res
+    }
+
+    async fn create_or_update(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        let client = self.client().await?;
+        create_ephemeral(&client, path, &data).await?;
+        self.nodes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.to_string(), data);
+        Ok(())
+    }
+}
+
+/// Creates the ephemeral node `path` in the current session, replacing any
+/// node left behind by a previous session of the same instance.
+async fn create_ephemeral(client: &Client, path: &str, data: &[u8]) -> Result<()> {
+    let parent = &path[..path.rfind('/').unwrap_or(0)];
+    if !parent.is_empty() {
+        client
+            .mkdir(
+                parent,
+                &CreateMode::Persistent.with_acls(Acls::anyone_all()),
+            )
+            .await
+            .map_err(|e| zk_error(parent, e))?;
+    }
+    let options = CreateMode::Ephemeral.with_acls(Acls::anyone_all());
+    match client.create(path, data, &options).await {
+        Err(zookeeper_client::Error::NodeExists) => {
+            match client.delete(path, None).await {
+                Ok(()) | Err(zookeeper_client::Error::NoNode) => {}
+                Err(e) => return Err(zk_error(path, e)),
+            }
+            client.create(path, data, &options).await
+        }
+        res => res,
+    }
+    .map(|_| ())
+    .map_err(|e| zk_error(path, e))
+}
+
+fn zk_error(path: &str, e: zookeeper_client::Error) -> Error {
+    match e {
+        zookeeper_client::Error::NoAuth | zookeeper_client::Error::AuthFailed => {
+            Error::Auth(format!("zookeeper ({path}): {e}"))
+        }
+        e => Error::transport(format!("zookeeper ({path})"), e),
+    }
+}
+
+#[async_trait]
+impl Registrar for ZookeeperRegistrar {
+    async fn login(&self) -> Result<()> {
+        self.client().await.map(|_| ())
+    }
+
+    async fn register_metadata(
+        &self,
+        metadata: &MetaDataRegister,
+        attempts: &mut Vec<Attempt>,
+    ) -> Result<()> {
+        self.register(
+            &metadata_path(metadata),
+            serde_json::to_vec(metadata)?,
+            attempts,
+        )
+        .await
+    }
+
+    async fn register_uri(&self, uri: &UriRegister, attempts: &mut Vec<Attempt>) -> Result<()> {
+        self.register(&uri_path(uri), serde_json::to_vec(uri)?, attempts)
+            .await
+    }
+
+    async fn offline(&self, uri: &UriRegister) -> Result<()> {
+        let path = uri_path(uri);
+        self.nodes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&path);
+        let client = self.client().await?;
+        match client.delete(&path, None).await {
+            Ok(()) | Err(zookeeper_client::Error::NoNode) => Ok(()),
+            Err(e) => Err(zk_error(&path, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::ShenyuClient;
+    use crate::model::UriInfo;
+    use crate::test_support::{config, FakeZookeeper};
+    use std::time::Duration;
+
+    fn client(zk: &FakeZookeeper) -> ShenyuClient {
+        let mut config = config(&zk.address);
+        config.register.register_type = "zookeeper".to_string();
+        let uri_infos = vec![UriInfo::new("/health", "get")];
+        ShenyuClient::builder()
+            .config(config)
+            .app_name("app1")
+            .uri_infos(&uri_infos)
+            .port(9527)
+            .build()
+            .unwrap()
+    }
+
+    fn uri_node(zk: &FakeZookeeper) -> Option<String> {
+        zk.paths()
+            .into_iter()
+            .find(|p| p.starts_with("/shenyu/register/uri/http/xxx/"))
+    }
+
+    #[tokio::test]
+    async fn test_register_ephemeral_nodes() {
+        let zk = FakeZookeeper::start();
+        let client = client(&zk);
+        client.register_async().await.unwrap();
+
+        let metadata = zk
+            .node("/shenyu/register/metadata/http/xxx/xxx..xxx.health")
+            .unwrap();
+        assert_ne!(metadata.ephemeral_owner, 0);
+        let metadata: serde_json::Value = serde_json::from_slice(&metadata.data).unwrap();
+        assert_eq!(metadata["path"], "/xxx/health");
+
+        let uri = uri_node(&zk).unwrap();
+        assert!(uri.ends_with(":9527"));
+        assert_ne!(zk.node(&uri).unwrap().ephemeral_owner, 0);
+        assert_eq!(
+            zk.node("/shenyu/register/uri/http")
+                .unwrap()
+                .ephemeral_owner,
+            0
+        );
+
+        // registering again replaces the nodes instead of failing
+        client.register_uri_async().await.unwrap();
+
+        client.offline_register_async().await.unwrap();
+        assert!(uri_node(&zk).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_nodes_vanish_with_session() {
+        let zk = FakeZookeeper::start();
+        let client = client(&zk);
+        client.register_async().await.unwrap();
+        assert!(uri_node(&zk).is_some());
+
+        drop(client);
+        for _ in 0..50 {
+            if uri_node(&zk).is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(uri_node(&zk).is_none());
+        assert!(zk.paths().iter().all(|p| !p.contains("xxx..xxx.health")));
+    }
+}
@@ -0,0 +1,217 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! What a registration did, item by item and server by server.
+
+use crate::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// One request to one server of the register center.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attempt {
+    /// The URL (or address) the request was sent to.
+    pub server: String,
+    /// The status code, when the server answered over HTTP.
+    pub status: Option<u16>,
+    /// The response body, when the server answered.
+    pub body: Option<String>,
+    pub latency: Duration,
+    /// Why the request failed, `None` when it succeeded.
+    pub error: Option<String>,
+}
+
+impl Attempt {
+    pub(crate) fn succeeded(
+        server: impl Into<String>,
+        status: Option<u16>,
+        body: Option<String>,
+        started: Instant,
+    ) -> Self {
+        Attempt {
+            server: server.into(),
+            status,
+            body,
+            latency: started.elapsed(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn failed(server: impl Into<String>, e: &Error, started: Instant) -> Self {
+        let (status, body) = match e {
+            Error::Rejected { status, body } => (Some(*status), Some(body.clone())),
+            _ => (None, None),
+        };
+        Attempt {
+            server: server.into(),
+            status,
+            body,
+            latency: started.elapsed(),
+            error: Some(e.to_string()),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// What was registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Metadata,
+    Uri,
+    DiscoveryConfig,
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ItemKind::Metadata => "metadata",
+            ItemKind::Uri => "uri",
+            ItemKind::DiscoveryConfig => "discovery config",
+        })
+    }
+}
+
+/// The registration of one metadata entry, URI or discovery config.
+#[derive(Debug)]
+pub struct ItemReport {
+    pub kind: ItemKind,
+    /// The path of a metadata entry, `host:port` of a URI, or the name of a
+    /// discovery config.
+    pub name: String,
+    /// Every request sent for this item, retries included, in order.
+    pub attempts: Vec<Attempt>,
+    /// Why the item couldn't be registered, `None` when it was.
+    pub error: Option<Error>,
+}
+
+impl ItemReport {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The outcome of registering to the register center.
+#[derive(Debug, Default)]
+pub struct RegistrationReport {
+    pub items: Vec<ItemReport>,
+}
+
+impl RegistrationReport {
+    /// Whether every item was registered.
+    pub fn is_success(&self) -> bool {
+        self.items.iter().all(ItemReport::is_success)
+    }
+
+    /// The items that couldn't be registered.
+    pub fn failures(&self) -> impl Iterator<Item = &ItemReport> {
+        self.items.iter().filter(|item| !item.is_success())
+    }
+
+    pub(crate) fn push(&mut self, item: ItemReport) {
+        self.items.push(item);
+    }
+
+    pub(crate) fn extend(&mut self, other: RegistrationReport) {
+        self.items.extend(other.items);
+    }
+
+    /// `Ok(self)` when every item was registered, [`Error::Incomplete`]
+    /// otherwise.
+    pub(crate) fn into_result(self) -> Result<Self, Error> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(Error::Incomplete(Box::new(self)))
+        }
+    }
+}
+
+impl fmt::Display for RegistrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            match &item.error {
+                None => writeln!(f, "[OK] {} {}", item.kind, item.name)?,
+                Some(e) => writeln!(f, "[FAILED] {} {}: {}", item.kind, item.name, e)?,
+            }
+            for attempt in &item.attempts {
+                write!(f, "  {} {:?}", attempt.server, attempt.latency)?;
+                if let Some(status) = attempt.status {
+                    write!(f, " status {}", status)?;
+                }
+                match (&attempt.error, &attempt.body) {
+                    (Some(e), _) => writeln!(f, " {}", e)?,
+                    (None, Some(body)) => writeln!(f, " {}", body)?,
+                    (None, None) => writeln!(f)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let started = Instant::now();
+        let rejected = Error::Rejected {
+            status: 500,
+            body: "db down".to_string(),
+        };
+        let mut report = RegistrationReport::default();
+        report.push(ItemReport {
+            kind: ItemKind::Uri,
+            name: "10.0.0.1:8080".to_string(),
+            attempts: vec![Attempt::succeeded(
+                "http://admin/uri",
+                Some(200),
+                Some("success".to_string()),
+                started,
+            )],
+            error: None,
+        });
+        assert!(report.is_success());
+
+        let attempt = Attempt::failed("http://admin/metadata", &rejected, started);
+        assert_eq!(attempt.status, Some(500));
+        assert_eq!(attempt.body.as_deref(), Some("db down"));
+        report.push(ItemReport {
+            kind: ItemKind::Metadata,
+            name: "/app/health".to_string(),
+            attempts: vec![attempt],
+            error: Some(rejected),
+        });
+        assert!(!report.is_success());
+        assert_eq!(report.failures().count(), 1);
+
+        let text = report.to_string();
+        assert!(text.contains("[OK] uri 10.0.0.1:8080"));
+        assert!(text.contains("[FAILED] metadata /app/health"));
+        assert!(text.contains("http://admin/metadata"));
+
+        let err = report.into_result().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "registration incomplete: 1 of 2 items failed"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Retries of register center calls, see [`RetryConfig`].
+
+use crate::config::{RetryConfig, RetryOn};
+use crate::error::{Error, Result};
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+impl RetryConfig {
+    /// Calls `op` until it succeeds, it fails in a way that isn't retryable,
+    /// or `max_attempts` is reached; returning the last outcome.
+    ///
+    /// Every attempt goes through all the servers of the register center, so
+    /// a retry only happens once all of them failed.
+    pub(crate) async fn run<T, F, Fut>(&self, what: &str, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let e = match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            let retryable = classify(&e).is_some_and(|failure| self.retry_on.contains(&failure));
+            if !retryable || attempt >= self.max_attempts {
+                return Err(e);
+            }
+            let delay = self.delay(attempt);
+            warn!(
+                "{} failed (attempt {}/{}): {}, retrying in {:?}",
+                what, attempt, self.max_attempts, e, delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// The exponential backoff before attempt `attempt + 1`, plus jitter.
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay_ms
+            .saturating_mul(1 << (attempt - 1).min(31))
+            .min(self.max_delay_ms);
+        let jitter = match self.jitter_ms {
+            0 => 0,
+            jitter => rand::thread_rng().gen_range(0..=jitter),
+        };
+        Duration::from_millis(backoff + jitter)
+    }
+}
+
+/// `None` for errors retrying can't fix, e.g. an invalid config or a payload
+/// that can't be serialized.
+fn classify(e: &Error) -> Option<RetryOn> {
+    match e {
+        Error::Transport { .. } => Some(RetryOn::Unreachable),
+        Error::Rejected { .. } | Error::Auth(_) => Some(RetryOn::Refused),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn config(max_attempts: u32, retry_on: Vec<RetryOn>) -> RetryConfig {
+        RetryConfig {
+            max_attempts,
+            base_delay_ms: 1,
+            max_delay_ms: 4,
+            jitter_ms: 0,
+            retry_on,
+        }
+    }
+
+    fn down() -> Error {
+        Error::transport("http://127.0.0.1:9095", "connection refused")
+    }
+
+    fn rejected() -> Error {
+        Error::Rejected {
+            status: 503,
+            body: "starting".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_delay() {
+        let config = RetryConfig {
+            max_attempts: 10,
+            base_delay_ms: 100,
+            max_delay_ms: 1_000,
+            jitter_ms: 0,
+            retry_on: vec![],
+        };
+        let delays: Vec<_> = (1..6).map(|a| config.delay(a).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1_000]);
+        assert_eq!(config.delay(64).as_millis(), 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_accepted() {
+        let calls = Cell::new(0);
+        let res = config(5, vec![RetryOn::Unreachable, RetryOn::Refused])
+            .run("test", || async {
+                calls.set(calls.get() + 1);
+                match calls.get() {
+                    1 => Err(down()),
+                    2 => Err(rejected()),
+                    _ => Ok(()),
+                }
+            })
+            .await;
+        assert!(res.is_ok());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_give_up() {
+        let calls = Cell::new(0);
+        let res = config(3, vec![RetryOn::Unreachable])
+            .run("test", || async {
+                calls.set(calls.get() + 1);
+                Err::<(), _>(down())
+            })
+            .await;
+        assert!(matches!(res, Err(Error::Transport { .. })));
+        assert_eq!(calls.get(), 3);
+
+        // refusals aren't retried unless configured
+        calls.set(0);
+        let res = config(3, vec![RetryOn::Unreachable])
+            .run("test", || async {
+                calls.set(calls.get() + 1);
+                Err::<(), _>(rejected())
+            })
+            .await;
+        assert!(matches!(res, Err(Error::Rejected { status: 503, .. })));
+        assert_eq!(calls.get(), 1);
+
+        // neither are errors retrying can't fix
+        calls.set(0);
+        let res = config(3, vec![RetryOn::Unreachable, RetryOn::Refused])
+            .run("test", || async {
+                calls.set(calls.get() + 1);
+                Err::<(), _>(Error::InvalidData("bad json".to_string()))
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
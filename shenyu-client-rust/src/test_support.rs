@@ -0,0 +1,372 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! In-process fakes shared by the unit tests.
+
+use crate::config::{EnvConfig, ShenYuConfig};
+use axum::body::Bytes;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::Router;
+use std::sync::{Arc, Mutex};
+
+/// A request received by a [`FakeServer`].
+#[derive(Debug, Clone)]
+pub(crate) struct Recorded {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl Recorded {
+    pub fn is(&self, method: Method, path: &str) -> bool {
+        self.method == method && self.path() == path
+    }
+
+    pub fn path(&self) -> &str {
+        self.uri.path()
+    }
+
+    /// The query and form-encoded body parameters.
+    pub fn params(&self) -> std::collections::HashMap<String, String> {
+        let query = self.uri.query().unwrap_or_default();
+        let url = format!("http://localhost/?{}&{}", query, self.body);
+        reqwest::Url::parse(&url)
+            .unwrap()
+            .query_pairs()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap_or_default()
+    }
+}
+
+type Responder = dyn Fn(&Recorded) -> (StatusCode, String) + Send + Sync;
+type ServerState = (Arc<Mutex<Vec<Recorded>>>, Arc<Responder>);
+
+/// A tiny HTTP server running on its own thread and runtime, recording every
+/// request and answering with `responder`.
+pub(crate) struct FakeServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl FakeServer {
+    pub fn start<F>(responder: F) -> Self
+    where
+        F: Fn(&Recorded) -> (StatusCode, String) + Send + Sync + 'static,
+    {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let state = (requests.clone(), Arc::new(responder) as Arc<Responder>);
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                let app = Router::new().fallback(handle).with_state(state);
+                axum::serve(listener, app).await.unwrap();
+            });
+        });
+        FakeServer { base_url, requests }
+    }
+
+    /// A fake ShenYu admin accepting every login and registration.
+    pub fn admin() -> Self {
+        Self::start(|req| match req.path() {
+            "/platform/login" => (
+                StatusCode::OK,
+                r#"{"code":200,"message":"login success","data":{"token":"fake-token"}}"#
+                    .to_string(),
+            ),
+            _ => (StatusCode::OK, "success".to_string()),
+        })
+    }
+
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn requests_to(&self, path: &str) -> Vec<Recorded> {
+        self.requests()
+            .into_iter()
+            .filter(|r| r.path() == path)
+            .collect()
+    }
+}
+
+async fn handle(
+    State((requests, responder)): State<ServerState>,
+    request: Request,
+) -> (StatusCode, String) {
+    let (parts, body) = request.into_parts();
+    let body: Bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    let recorded = Recorded {
+        method: parts.method,
+        uri: parts.uri,
+        headers: parts.headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    };
+    let response = responder(&recorded);
+    requests.lock().unwrap().push(recorded);
+    response
+}
+
+/// A config pointing at `servers` with the same values as `config.yml`.
+pub(crate) fn config(servers: &str) -> ShenYuConfig {
+    serde_yaml::from_str::<EnvConfig>(&config_yaml(servers))
+        .unwrap()
+        .shenyu
+}
+
+pub(crate) fn config_yaml(servers: &str) -> String {
+    format!(
+        r#"
+shenyu:
+  register:
+    register_type: "http"
+    servers: "{servers}"
+    props:
+      username: "admin"
+      password: "123456"
+  uri:
+    app_name: "app1"
+    context_path: "/xxx"
+    environment: "test"
+    rpc_type: "http"
+  discovery:
+    protocol: "http://"
+    discovery_type: "zookeeper"
+    server_lists: "127.0.0.1:2181"
+    register_path: "/shenyu/discovery/http_example"
+    plugin_name: ""
+    props:
+      maxRetries: 4
+"#
+    )
+}
+
+/// A znode held by a [`FakeZookeeper`].
+#[derive(Debug, Clone)]
+pub(crate) struct ZNode {
+    pub data: Vec<u8>,
+    pub ephemeral_owner: i64,
+}
+
+type ZNodes = Arc<Mutex<std::collections::BTreeMap<String, ZNode>>>;
+
+/// An in-process ZooKeeper server speaking enough of the wire protocol for
+/// sessions, `create`, `delete` and pings. Ephemeral nodes are dropped as soon
+/// as the connection of their session closes.
+pub(crate) struct FakeZookeeper {
+    pub address: String,
+    nodes: ZNodes,
+}
+
+impl FakeZookeeper {
+    pub fn start() -> Self {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let nodes: ZNodes = Arc::default();
+        let server_nodes = nodes.clone();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                let mut session_id = 0x1000;
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    session_id += 1;
+                    tokio::spawn(zk_session(stream, session_id, server_nodes.clone()));
+                }
+            });
+        });
+        FakeZookeeper { address, nodes }
+    }
+
+    pub fn node(&self, path: &str) -> Option<ZNode> {
+        self.nodes.lock().unwrap().get(path).cloned()
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        self.nodes.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+const ZK_NO_NODE: i32 = -101;
+const ZK_NODE_EXISTS: i32 = -110;
+const ZK_NOT_EMPTY: i32 = -111;
+const ZK_UNIMPLEMENTED: i32 = -6;
+
+async fn zk_session(mut stream: tokio::net::TcpStream, session_id: i64, nodes: ZNodes) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn read_frame(stream: &mut tokio::net::TcpStream) -> Option<Vec<u8>> {
+        let len = stream.read_i32().await.ok()?;
+        let mut frame = vec![0; len as usize];
+        stream.read_exact(&mut frame).await.ok()?;
+        Some(frame)
+    }
+
+    // ConnectRequest, answered with a fixed timeout and password.
+    if read_frame(&mut stream).await.is_none() {
+        return;
+    }
+    let mut response = Vec::new();
+    response.extend(0i32.to_be_bytes());
+    response.extend(30_000i32.to_be_bytes());
+    response.extend(session_id.to_be_bytes());
+    response.extend(16i32.to_be_bytes());
+    response.extend([0u8; 16]);
+    response.push(0);
+    let mut zxid = 0i64;
+    let mut out = (response.len() as i32).to_be_bytes().to_vec();
+    out.extend(response);
+    if stream.write_all(&out).await.is_err() {
+        return;
+    }
+
+    while let Some(frame) = read_frame(&mut stream).await {
+        let mut buf = ZkBuf(&frame);
+        let xid = buf.i32();
+        let op = buf.i32();
+        zxid += 1;
+        let (err, body) = match op {
+            // create, create2
+            1 | 15 => {
+                let path = buf.string();
+                let data = buf.buffer();
+                for _ in 0..buf.i32() {
+                    buf.i32();
+                    buf.string();
+                    buf.string();
+                }
+                let flags = buf.i32();
+                let mut nodes = nodes.lock().unwrap();
+                let parent = &path[..path.rfind('/').unwrap_or(0)];
+                if nodes.contains_key(&path) {
+                    (ZK_NODE_EXISTS, vec![])
+                } else if !parent.is_empty() && !nodes.contains_key(parent) {
+                    (ZK_NO_NODE, vec![])
+                } else {
+                    let ephemeral_owner = if flags & 1 == 1 { session_id } else { 0 };
+                    nodes.insert(
+                        path.clone(),
+                        ZNode {
+                            data,
+                            ephemeral_owner,
+                        },
+                    );
+                    let mut body = (path.len() as i32).to_be_bytes().to_vec();
+                    body.extend(path.as_bytes());
+                    if op == 15 {
+                        body.extend(zk_stat(zxid, ephemeral_owner));
+                    }
+                    (0, body)
+                }
+            }
+            // delete
+            2 => {
+                let path = buf.string();
+                let mut nodes = nodes.lock().unwrap();
+                let prefix = format!("{path}/");
+                if !nodes.contains_key(&path) {
+                    (ZK_NO_NODE, vec![])
+                } else if nodes.keys().any(|k| k.starts_with(&prefix)) {
+                    (ZK_NOT_EMPTY, vec![])
+                } else {
+                    nodes.remove(&path);
+                    (0, vec![])
+                }
+            }
+            // ping, auth
+            11 | 100 => (0, vec![]),
+            // close session
+            -11 => break,
+            _ => (ZK_UNIMPLEMENTED, vec![]),
+        };
+        let mut reply = Vec::new();
+        reply.extend(xid.to_be_bytes());
+        reply.extend(zxid.to_be_bytes());
+        reply.extend(err.to_be_bytes());
+        if err == 0 {
+            reply.extend(body);
+        }
+        let mut out = (reply.len() as i32).to_be_bytes().to_vec();
+        out.extend(reply);
+        if stream.write_all(&out).await.is_err() {
+            break;
+        }
+    }
+    nodes
+        .lock()
+        .unwrap()
+        .retain(|_, node| node.ephemeral_owner != session_id);
+}
+
+fn zk_stat(zxid: i64, ephemeral_owner: i64) -> Vec<u8> {
+    let mut stat = Vec::new();
+    for v in [zxid, zxid, 0, 0] {
+        stat.extend(v.to_be_bytes());
+    }
+    for v in [0i32, 0, 0] {
+        stat.extend(v.to_be_bytes());
+    }
+    stat.extend(ephemeral_owner.to_be_bytes());
+    for v in [0i32, 0] {
+        stat.extend(v.to_be_bytes());
+    }
+    stat.extend(zxid.to_be_bytes());
+    stat
+}
+
+struct ZkBuf<'a>(&'a [u8]);
+
+impl ZkBuf<'_> {
+    fn i32(&mut self) -> i32 {
+        let (head, tail) = self.0.split_at(4);
+        self.0 = tail;
+        i32::from_be_bytes(head.try_into().unwrap())
+    }
+
+    fn buffer(&mut self) -> Vec<u8> {
+        let len = self.i32();
+        if len <= 0 {
+            return vec![];
+        }
+        let (head, tail) = self.0.split_at(len as usize);
+        self.0 = tail;
+        head.to_vec()
+    }
+
+    fn string(&mut self) -> String {
+        String::from_utf8(self.buffer()).unwrap()
+    }
+}
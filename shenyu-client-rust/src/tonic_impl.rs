@@ -0,0 +1,507 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! gRPC services served with tonic, exposed through the ShenYu `grpc`
+//! plugin.
+
+use crate::error::{Error, Result};
+use crate::model::UriInfo;
+use crate::IRouter;
+use prost::Message;
+use prost_types::FileDescriptorSet;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::time::Duration;
+use tonic::body::BoxBody;
+use tonic::codegen::http::{Request, Response};
+use tonic::server::NamedService;
+use tonic::service::Routes;
+use tonic::transport::server::{Router, Server};
+use tower_service::Service;
+
+/// The `rpcType` of the routes of a [`ShenYuGrpcRouter`].
+pub const GRPC_RPC_TYPE: &str = "grpc";
+
+/// The timeout of the methods, unless told otherwise with
+/// [`ShenYuGrpcRouter::timeout`].
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(5000);
+
+/// The parameter every method of the Java client takes, the one the
+/// response is sent to.
+const STREAM_OBSERVER: &str = "io.grpc.stub.StreamObserver";
+
+/// How a method streams its request and response, as told to the `grpc`
+/// plugin by the `methodType` of the `rpcExt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MethodType {
+    Unary,
+    ClientStreaming,
+    ServerStreaming,
+    BidiStreaming,
+}
+
+/// A method of a file descriptor set.
+#[derive(Debug, Clone)]
+struct Method {
+    input_type: String,
+    method_type: MethodType,
+}
+
+/// The `rpcExt` of a gRPC method.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GrpcExt {
+    timeout: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    method_type: Option<MethodType>,
+}
+
+/// A router registering the methods of its tonic services, each as the
+/// metadata of one ShenYu rule with `rpcType: grpc`.
+///
+/// The methods, their `parameterTypes` and the `methodType` of the
+/// `rpcExt` are read from the file descriptor set tonic-build emits, given
+/// with [`ShenYuGrpcRouter::file_descriptor_set`]; without one, the methods
+/// are named with [`ShenYuGrpcRouter::add_service_methods`].
+///
+/// # Examples
+/// ```rust,ignore
+/// use shenyu_client_rust::tonic_impl::ShenYuGrpcRouter;
+/// use tonic::transport::server::{Router, Server};
+///
+/// let app = ShenYuGrpcRouter::new("grpc_app", Server::builder())
+///     .file_descriptor_set(FILE_DESCRIPTOR_SET)?
+///     .add_service(GreeterServer::new(MyGreeter))?;
+/// let client = ShenyuClient::builder()
+///     .config_file("config.yml")
+///     .router(&app)
+///     .port(50051)
+///     .build()?;
+///
+/// let router: Router = app.into();
+/// router.serve("0.0.0.0:50051".parse()?).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShenYuGrpcRouter<L> {
+    app_name: String,
+    server: Server<L>,
+    routes: Routes,
+    uri_infos: Vec<UriInfo>,
+    /// by path, `/{service}/{method}`
+    methods: HashMap<String, Method>,
+    timeout: Duration,
+}
+
+impl<L> ShenYuGrpcRouter<L> {
+    /// A router serving its services with `server`.
+    pub fn new(app_name: &str, server: Server<L>) -> Self {
+        Self {
+            app_name: app_name.to_string(),
+            server,
+            routes: Routes::default(),
+            uri_infos: Vec::new(),
+            methods: HashMap::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Describes the methods with the file descriptor set encoded in
+    /// `encoded`, such as the one tonic-build writes with
+    /// `file_descriptor_set_path` for `tonic-reflection`; may be called once
+    /// per set.
+    pub fn file_descriptor_set(mut self, encoded: &[u8]) -> Result<Self> {
+        let set = FileDescriptorSet::decode(encoded)
+            .map_err(|e| Error::InvalidData(format!("file descriptor set: {e}")))?;
+        for file in &set.file {
+            for service in &file.service {
+                let service_name = match file.package() {
+                    "" => service.name().to_string(),
+                    package => format!("{package}.{}", service.name()),
+                };
+                for method in &service.method {
+                    let method_type = match (method.client_streaming(), method.server_streaming()) {
+                        (false, false) => MethodType::Unary,
+                        (true, false) => MethodType::ClientStreaming,
+                        (false, true) => MethodType::ServerStreaming,
+                        (true, true) => MethodType::BidiStreaming,
+                    };
+                    self.methods.insert(
+                        format!("/{service_name}/{}", method.name()),
+                        Method {
+                            input_type: method.input_type().trim_start_matches('.').to_string(),
+                            method_type,
+                        },
+                    );
+                }
+            }
+        }
+        self.describe();
+        Ok(self)
+    }
+
+    /// The `timeout` of the `rpcExt` of the methods, [`DEFAULT_TIMEOUT`]
+    /// otherwise.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.describe();
+        self
+    }
+
+    /// Serves `service`, registering each of its methods listed by the file
+    /// descriptor sets, which must be loaded beforehand with
+    /// [`Self::file_descriptor_set`]; a service they don't list is an error.
+    pub fn add_service<S>(self, service: S) -> Result<Self>
+    where
+        S: Service<Request<BoxBody>, Response = Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        let prefix = format!("/{}/", S::NAME);
+        let mut methods: Vec<&str> = self
+            .methods
+            .keys()
+            .filter_map(|path| path.strip_prefix(&prefix))
+            .collect();
+        if methods.is_empty() {
+            return Err(Error::Config(format!(
+                "{} is not in the file descriptor sets",
+                S::NAME
+            )));
+        }
+        methods.sort_unstable();
+        let methods: Vec<String> = methods.into_iter().map(str::to_string).collect();
+        Ok(self.serve(service, &methods))
+    }
+
+    /// Serves `service`, registering `methods`, named as in the `.proto`
+    /// file, e.g. `SayHello`. An empty name is an error, and so is a name
+    /// missing from the file descriptor sets when any is loaded.
+    pub fn add_service_methods<S>(self, service: S, methods: &[&str]) -> Result<Self>
+    where
+        S: Service<Request<BoxBody>, Response = Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        if methods.iter().any(|method| method.trim().is_empty()) {
+            return Err(Error::Config(format!(
+                "{}: a method name is empty",
+                S::NAME
+            )));
+        }
+        if !self.methods.is_empty() {
+            if let Some(method) = methods
+                .iter()
+                .find(|method| !self.methods.contains_key(&format!("/{}/{method}", S::NAME)))
+            {
+                return Err(Error::Config(format!(
+                    "{}/{method} is not in the file descriptor sets",
+                    S::NAME
+                )));
+            }
+        }
+        Ok(self.serve(service, methods))
+    }
+
+    fn serve<S>(mut self, service: S, methods: &[impl AsRef<str>]) -> Self
+    where
+        S: Service<Request<BoxBody>, Response = Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        self.routes = self.routes.add_service(service);
+        self.uri_infos.extend(
+            methods
+                .iter()
+                .map(|method| grpc_uri_info(S::NAME, method.as_ref())),
+        );
+        self.describe();
+        self
+    }
+
+    /// Fills the `parameterTypes` and the `rpcExt` of the methods, as the
+    /// Java client does: the request type then [`STREAM_OBSERVER`] when the
+    /// request is not streamed, [`STREAM_OBSERVER`] alone otherwise.
+    fn describe(&mut self) {
+        for uri_info in &mut self.uri_infos {
+            let method = self.methods.get(&uri_info.path);
+            uri_info.parameter_types = match method {
+                Some(method) => match method.method_type {
+                    MethodType::Unary | MethodType::ServerStreaming => {
+                        format!("{},{STREAM_OBSERVER}", method.input_type)
+                    }
+                    MethodType::ClientStreaming | MethodType::BidiStreaming => {
+                        STREAM_OBSERVER.to_string()
+                    }
+                },
+                None => String::new(),
+            };
+            let ext = GrpcExt {
+                timeout: self.timeout.as_millis(),
+                method_type: method.map(|method| method.method_type),
+            };
+            uri_info.rpc_ext = serde_json::to_string(&ext).unwrap_or_default();
+        }
+    }
+
+    pub fn uri_infos(&self) -> &Vec<UriInfo> {
+        &self.uri_infos
+    }
+}
+
+/// The route of the gRPC method `method` of `service`, the full name of
+/// the service such as `helloworld.Greeter`.
+fn grpc_uri_info(service: &str, method: &str) -> UriInfo {
+    UriInfo::new(&format!("/{service}/{method}"), method).service_name(service)
+}
+
+impl<L: Clone> From<ShenYuGrpcRouter<L>> for Router<L> {
+    fn from(mut val: ShenYuGrpcRouter<L>) -> Self {
+        val.server.add_routes(val.routes)
+    }
+}
+
+impl<L> IRouter for ShenYuGrpcRouter<L> {
+    fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    fn uri_infos(&self) -> &Vec<UriInfo> {
+        &self.uri_infos
+    }
+
+    fn rpc_type(&self) -> Option<&str> {
+        Some(GRPC_RPC_TYPE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ShenyuClient, REGISTER_META_DATA_SUFFIX, REGISTER_URI_SUFFIX};
+    use crate::test_support::{config, FakeServer};
+    use std::future::{ready, Ready};
+    use std::task::{Context, Poll};
+
+    /// What tonic-build generates for `service Greeter` of `package
+    /// helloworld`, answering nothing.
+    #[derive(Clone)]
+    struct GreeterServer;
+
+    impl NamedService for GreeterServer {
+        const NAME: &'static str = "helloworld.Greeter";
+    }
+
+    impl Service<Request<BoxBody>> for GreeterServer {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: Request<BoxBody>) -> Self::Future {
+            ready(Ok(Response::new(tonic::body::empty_body())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_grpc() {
+        let app = ShenYuGrpcRouter::new("grpc_app", Server::builder())
+            .add_service_methods(GreeterServer, &["SayHello", "SayHelloAgain"])
+            .unwrap();
+        let names: Vec<_> = app
+            .uri_infos()
+            .iter()
+            .map(|x| (x.method_name.as_str(), x.rule_name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("SayHello", "/helloworld.Greeter/SayHello"),
+                ("SayHelloAgain", "/helloworld.Greeter/SayHelloAgain"),
+            ]
+        );
+        let admin = FakeServer::admin();
+        let client = ShenyuClient::builder()
+            .config(config(&admin.base_url))
+            .router(&app)
+            .port(50051)
+            .build()
+            .unwrap();
+        client.register_all_metadata_async(true).await.unwrap();
+        client.register_uri_async().await.unwrap();
+
+        let metadata = admin.requests_to(REGISTER_META_DATA_SUFFIX);
+        assert_eq!(metadata.len(), 2);
+        let say_hello = metadata[0].json();
+        assert_eq!(say_hello["appName"], "grpc_app");
+        assert_eq!(say_hello["rpcType"], "grpc");
+        assert_eq!(say_hello["serviceName"], "helloworld.Greeter");
+        assert_eq!(say_hello["methodName"], "SayHello");
+        assert_eq!(say_hello["path"], "/xxx/helloworld.Greeter/SayHello");
+        assert_eq!(say_hello["ruleName"], "/xxx/helloworld.Greeter/SayHello");
+        assert_eq!(say_hello["rpcExt"], r#"{"timeout":5000}"#);
+        assert_eq!(metadata[1].json()["methodName"], "SayHelloAgain");
+        let uri = &admin.requests_to(REGISTER_URI_SUFFIX)[0];
+        assert_eq!(uri.json()["rpcType"], "grpc");
+        assert_eq!(uri.json()["port"], 50051);
+
+        let _router: Router<_> = app.into();
+
+        let err = ShenYuGrpcRouter::new("grpc_app", Server::builder())
+            .add_service_methods(GreeterServer, &["SayHello", " "])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: helloworld.Greeter: a method name is empty"
+        );
+    }
+
+    /// What tonic-build emits for `helloworld.proto`.
+    fn helloworld() -> Vec<u8> {
+        use prost_types::{FileDescriptorProto, MethodDescriptorProto, ServiceDescriptorProto};
+
+        let method = |name: &str, server_streaming| MethodDescriptorProto {
+            name: Some(name.to_string()),
+            input_type: Some(".helloworld.HelloRequest".to_string()),
+            output_type: Some(".helloworld.HelloReply".to_string()),
+            server_streaming: Some(server_streaming),
+            ..Default::default()
+        };
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("helloworld.proto".to_string()),
+                package: Some("helloworld".to_string()),
+                service: vec![ServiceDescriptorProto {
+                    name: Some("Greeter".to_string()),
+                    method: vec![method("SayHello", false), method("LotsOfReplies", true)],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_file_descriptor_set() {
+        #[derive(Clone)]
+        struct ReflectionServer;
+
+        impl NamedService for ReflectionServer {
+            const NAME: &'static str = "grpc.reflection.v1.ServerReflection";
+        }
+
+        impl Service<Request<BoxBody>> for ReflectionServer {
+            type Response = Response<BoxBody>;
+            type Error = Infallible;
+            type Future = Ready<Result<Self::Response, Self::Error>>;
+
+            fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                GreeterServer.poll_ready(cx)
+            }
+
+            fn call(&mut self, req: Request<BoxBody>) -> Self::Future {
+                GreeterServer.call(req)
+            }
+        }
+
+        let app = ShenYuGrpcRouter::new("grpc_app", Server::builder())
+            .file_descriptor_set(&helloworld())
+            .unwrap()
+            .file_descriptor_set(tonic_reflection::pb::v1::FILE_DESCRIPTOR_SET)
+            .unwrap()
+            .add_service(GreeterServer)
+            .unwrap()
+            .add_service(ReflectionServer)
+            .unwrap()
+            .timeout(Duration::from_secs(3));
+        let described: Vec<_> = app
+            .uri_infos()
+            .iter()
+            .map(|x| {
+                (
+                    x.path.as_str(),
+                    x.parameter_types.as_str(),
+                    x.rpc_ext.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            described,
+            [
+                (
+                    "/helloworld.Greeter/LotsOfReplies",
+                    "helloworld.HelloRequest,io.grpc.stub.StreamObserver",
+                    r#"{"timeout":3000,"methodType":"SERVER_STREAMING"}"#
+                ),
+                (
+                    "/helloworld.Greeter/SayHello",
+                    "helloworld.HelloRequest,io.grpc.stub.StreamObserver",
+                    r#"{"timeout":3000,"methodType":"UNARY"}"#
+                ),
+                (
+                    "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo",
+                    "io.grpc.stub.StreamObserver",
+                    r#"{"timeout":3000,"methodType":"BIDI_STREAMING"}"#
+                ),
+            ]
+        );
+
+        let greeter = || {
+            ShenYuGrpcRouter::new("grpc_app", Server::builder())
+                .file_descriptor_set(&helloworld())
+                .unwrap()
+        };
+        let app = greeter()
+            .add_service_methods(GreeterServer, &["SayHello"])
+            .unwrap();
+        assert_eq!(
+            app.uri_infos()[0].rpc_ext,
+            r#"{"timeout":5000,"methodType":"UNARY"}"#
+        );
+        let err = greeter()
+            .add_service_methods(GreeterServer, &["SayHello", "Unknown"])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: helloworld.Greeter/Unknown is not in the file descriptor sets"
+        );
+        let err = greeter().add_service(ReflectionServer).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: grpc.reflection.v1.ServerReflection is not in the file descriptor sets"
+        );
+
+        let err = ShenYuGrpcRouter::new("grpc_app", Server::builder())
+            .file_descriptor_set(b"not a descriptor")
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidData(_)));
+    }
+}
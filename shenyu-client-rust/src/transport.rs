@@ -0,0 +1,249 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The HTTP client shared by every call to the register center.
+
+use crate::config::{HttpConfig, RegisterConfig, ShenYuConfig, TlsConfig};
+use crate::error::{Error, Result};
+use crate::registrar;
+use reqwest::{Certificate, ClientBuilder, Identity, NoProxy, Proxy, Url};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// Builds the HTTP client for `config`, from its `http` section and the TLS
+/// settings of the register center.
+///
+/// With `register.tls.server_name`, the client resolves that name to the
+/// IP of the server, whose URLs use the name instead, see
+/// [`registrar::register_urls`].
+pub(crate) fn http_client(config: &ShenYuConfig) -> Result<reqwest::Client> {
+    let mut builder = http(reqwest::Client::builder(), &config.http)?;
+    builder = tls(builder, &config.register.tls)?;
+    if let Some((name, addr)) = pinned_server(&config.register)? {
+        builder = builder.resolve(name, addr);
+    }
+    builder
+        .build()
+        .map_err(|e| Error::Config(format!("http client: {e}")))
+}
+
+/// `register.tls.server_name` and the address it stands for: that of the
+/// single server of `register.servers`, which must be addressed by IP, as
+/// one name can't tell several servers apart.
+pub(crate) fn pinned_server(register: &RegisterConfig) -> Result<Option<(&str, SocketAddr)>> {
+    let Some(name) = register.tls.server_name.as_deref() else {
+        return Ok(None);
+    };
+    let urls = registrar::server_urls(&register.servers);
+    let [url] = urls.as_slice() else {
+        return Err(Error::Config(format!(
+            "register.tls.server_name names a single server, but register.servers has {}",
+            urls.len()
+        )));
+    };
+    match pin_server_name(url, name) {
+        // the port of the URL is used, whatever the one of the address
+        Some((_, ip)) => Ok(Some((name, SocketAddr::new(ip, 0)))),
+        None => Err(Error::Config(format!(
+            "register.tls.server_name only applies to a server addressed by IP, not {url}"
+        ))),
+    }
+}
+
+fn http(mut builder: ClientBuilder, http: &HttpConfig) -> Result<ClientBuilder> {
+    builder = builder
+        .connect_timeout(Duration::from_millis(http.connect_timeout_ms))
+        .read_timeout(Duration::from_millis(http.read_timeout_ms))
+        .pool_max_idle_per_host(http.pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_millis(http.pool_idle_timeout_ms))
+        .user_agent(&http.user_agent);
+    if let Some(proxy) = &http.proxy {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| Error::Config(format!("http.proxy ({proxy}): {e}")))?
+            .no_proxy(http.no_proxy.as_deref().and_then(NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+    Ok(builder)
+}
+
+fn tls(mut builder: ClientBuilder, tls: &TlsConfig) -> Result<ClientBuilder> {
+    let read = |key: &str, path: &str| {
+        std::fs::read(path).map_err(|e| Error::Config(format!("register.tls.{key} ({path}): {e}")))
+    };
+    let invalid = |key: &str, e: reqwest::Error| Error::Config(format!("register.tls.{key}: {e}"));
+
+    if let Some(path) = &tls.ca_file {
+        let certificates = Certificate::from_pem_bundle(&read("ca_file", path)?)
+            .map_err(|e| invalid("ca_file", e))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    match (&tls.cert_file, &tls.key_file) {
+        (Some(cert), Some(key)) => {
+            let mut pem = read("cert_file", cert)?;
+            pem.push(b'\n');
+            pem.extend(read("key_file", key)?);
+            let identity = Identity::from_pem(&pem).map_err(|e| invalid("cert_file", e))?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err(Error::Config(
+                "register.tls.cert_file and key_file must be set together".to_string(),
+            ))
+        }
+    }
+    if tls.insecure_skip_verify {
+        builder = builder
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true);
+    }
+    Ok(builder)
+}
+
+/// Rewrites `url` to use `name` instead of its IP host, returning it along
+/// with that IP; `None` when the host is not an IP.
+pub(crate) fn pin_server_name(url: &str, name: &str) -> Option<(String, IpAddr)> {
+    let mut url = Url::parse(url).ok()?;
+    let ip = url
+        .host_str()?
+        .trim_matches(['[', ']'])
+        .parse::<IpAddr>()
+        .ok()?;
+    url.set_host(Some(name)).ok()?;
+    Some((url.as_str().trim_end_matches('/').to_string(), ip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, FakeServer};
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_pin_server_name() {
+        let pin = |url: &str| pin_server_name(url, "admin.internal");
+        assert_eq!(
+            pin("https://10.0.0.1:9095"),
+            Some((
+                "https://admin.internal:9095".to_string(),
+                "10.0.0.1".parse().unwrap()
+            ))
+        );
+        assert_eq!(
+            pin("https://[::1]:9095/admin"),
+            Some((
+                "https://admin.internal:9095/admin".to_string(),
+                "::1".parse().unwrap()
+            ))
+        );
+        assert_eq!(pin("https://admin.example:9095"), None);
+    }
+
+    #[test]
+    fn test_server_name() {
+        let mut config = config("https://10.0.0.1:9095/");
+        config.register.tls.server_name = Some("admin.internal".to_string());
+        http_client(&config).unwrap();
+        assert_eq!(config.register.servers, "https://10.0.0.1:9095/");
+        assert_eq!(
+            registrar::register_urls(&config.register),
+            ["https://admin.internal:9095"]
+        );
+
+        // one name can't stand for several servers, nor replace a host name
+        config.register.servers = "https://10.0.0.1:9095,https://10.0.0.2:9095".to_string();
+        let err = http_client(&config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.tls.server_name names a single server, \
+             but register.servers has 2"
+        );
+        config.register.servers = "https://admin.example:9095".to_string();
+        let err = http_client(&config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.tls.server_name only applies to a server addressed by IP, \
+             not https://admin.example:9095"
+        );
+    }
+
+    #[test]
+    fn test_invalid_tls_files() {
+        let mut config = config("https://10.0.0.1:9095");
+        let register = &mut config.register;
+        register.tls.ca_file = Some("/nonexistent/ca.pem".to_string());
+        let err = tls(reqwest::Client::builder(), &register.tls).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("register.tls.ca_file (/nonexistent/ca.pem)"));
+
+        let not_pem = std::env::current_dir().unwrap().join("config.yml");
+        register.tls.ca_file = None;
+        register.tls.cert_file = Some(not_pem.to_str().unwrap().to_string());
+        let err = tls(reqwest::Client::builder(), &register.tls).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: register.tls.cert_file and key_file must be set together"
+        );
+        register.tls.key_file = register.tls.cert_file.clone();
+        let err = tls(reqwest::Client::builder(), &register.tls).unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+    }
+
+    #[test]
+    fn test_insecure_skip_verify() {
+        let mut config = config("https://10.0.0.1:9095");
+        config.register.tls.insecure_skip_verify = true;
+        assert!(http_client(&config).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() {
+        let admin = FakeServer::start(|_| {
+            std::thread::sleep(Duration::from_millis(500));
+            (StatusCode::OK, "success".to_string())
+        });
+        let mut config = config(&admin.base_url);
+        config.http.read_timeout_ms = 50;
+        let client = http_client(&config).unwrap();
+        let err = client.get(&admin.base_url).send().await.unwrap_err();
+        assert!(err.is_timeout());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_and_user_agent() {
+        let proxy = FakeServer::start(|_| (StatusCode::OK, "success".to_string()));
+        let mut config = config("http://admin.internal:9095");
+        config.http.proxy = Some(proxy.base_url.clone());
+        config.http.user_agent = "my-service/1.0".to_string();
+        let client = http_client(&config).unwrap();
+        client
+            .get("http://admin.internal:9095/platform/login")
+            .send()
+            .await
+            .unwrap();
+
+        let requests = proxy.requests();
+        assert_eq!(
+            requests[0].uri.to_string(),
+            "http://admin.internal:9095/platform/login"
+        );
+        assert_eq!(requests[0].headers["user-agent"], "my-service/1.0");
+    }
+}